@@ -1,7 +1,9 @@
 use std::fmt::{Display, Error, Formatter};
+use std::ops::Deref;
+use std::str::FromStr;
 use board;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Coordinate {
     pub row: usize,
     pub col: usize,
@@ -17,13 +19,8 @@ impl Coordinate {
         }
     }
 
-    fn unchecked_move_in(&self, dir: Direction, delta: usize) -> Coordinate {
-        match dir {
-            Direction::Up => Coordinate{row: self.row - delta, col: self.col},
-            Direction::Down => Coordinate{row: self.row + delta, col: self.col},
-            Direction::Left => Coordinate{row: self.row, col: self.col - delta},
-            Direction::Right => Coordinate{row: self.row, col: self.col + delta},
-        }
+    fn on_board(&self) -> bool {
+        self.row < board::NUM_ROWS && self.col < board::NUM_COLS
     }
 
     pub fn induces_board_type(&self) -> bool {
@@ -52,7 +49,7 @@ pub const POSSIBLE_DIRECTIONS: [Direction; 4] = [
     Direction::Right,
 ];
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum MoveType {
     Single,
     OneUp,
@@ -80,12 +77,24 @@ pub const POSSIBLE_MOVE_TYPES: [MoveType; 11] = [
     MoveType::LeftAndRight,
 ];
 
+impl FromStr for MoveType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MoveType, String> {
+        POSSIBLE_MOVE_TYPES.iter().cloned().find(|mt| {
+            format!("{:?}", mt).eq_ignore_ascii_case(s)
+        }).ok_or_else(|| format!("Unknown move type {}", s))
+    }
+}
+
 #[derive(Clone)]
 pub struct MoveEffect {
     pub old_board_type: Option<board::BoardType>,
+    // The board's frontier before the move, restored when it is undone.
+    pub old_frontier: board::BoardArray,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Move {
     pub coord: Coordinate,
     move_type: MoveType,
@@ -94,7 +103,7 @@ pub struct Move {
 
 impl Move {
     pub fn new(coord: Coordinate, move_type: MoveType) -> Option<Move> {
-        let c = Move{coord: coord, move_type: move_type, new_board_type: None};
+        let c = Move{coord, move_type, new_board_type: None};
         if c.in_bounds() { Some(c) } else { None }
     }
 
@@ -103,6 +112,9 @@ impl Move {
     }
 
     pub fn in_bounds(&self) -> bool {
+        if !self.coord.on_board() {
+            return false;
+        }
         match self.move_type {
             MoveType::Single => true,
             MoveType::OneUp => self.coord.row >= 1,
@@ -118,20 +130,124 @@ impl Move {
         }
     }
 
-    pub fn extensions(&self) -> Vec<Coordinate> {
-        match self.move_type {
-            MoveType::Single => vec![],
-            MoveType::OneUp => vec![self.coord.unchecked_move_in(Direction::Up, 1)],
-            MoveType::OneDown => vec![self.coord.unchecked_move_in(Direction::Down, 1)],
-            MoveType::OneLeft => vec![self.coord.unchecked_move_in(Direction::Left, 1)],
-            MoveType::OneRight => vec![self.coord.unchecked_move_in(Direction::Right, 1)],
-            MoveType::TwoUp => vec![self.coord.unchecked_move_in(Direction::Up, 1), self.coord.unchecked_move_in(Direction::Up, 2)],
-            MoveType::TwoDown => vec![self.coord.unchecked_move_in(Direction::Down, 1), self.coord.unchecked_move_in(Direction::Down, 2)],
-            MoveType::TwoLeft => vec![self.coord.unchecked_move_in(Direction::Left, 1), self.coord.unchecked_move_in(Direction::Left, 2)],
-            MoveType::TwoRight => vec![self.coord.unchecked_move_in(Direction::Right, 1), self.coord.unchecked_move_in(Direction::Right, 2)],
-            MoveType::UpAndDown => vec![self.coord.unchecked_move_in(Direction::Up, 1), self.coord.unchecked_move_in(Direction::Down, 1)],
-            MoveType::LeftAndRight => vec![self.coord.unchecked_move_in(Direction::Left, 1), self.coord.unchecked_move_in(Direction::Right, 1)],
+    // Every square the move places track on, in row-major order.
+    // A move running off the board only has the squares that are on it.
+    pub fn squares(&self) -> Squares {
+        let mut squares = self.extensions();
+        if self.coord.on_board() {
+            squares.push(self.coord);
         }
+        squares.squares[..squares.len].sort_by_key(|c| (c.row, c.col));
+        squares
+    }
+
+    // The move placing the same squares, with the same board type, written from its
+    // top-left square as Single, OneRight, OneDown, TwoRight or TwoDown.
+    // A move running off the board is left as it is, for `Board::check_move` to reject.
+    pub fn canonical(&self) -> Move {
+        if !self.in_bounds() {
+            return *self;
+        }
+        let squares = self.squares();
+        let first = squares[0];
+        let last = squares[squares.len() - 1];
+        let move_type = match (squares.len(), first.row == last.row) {
+            (1, _) => MoveType::Single,
+            (2, true) => MoveType::OneRight,
+            (2, false) => MoveType::OneDown,
+            (_, true) => MoveType::TwoRight,
+            (_, false) => MoveType::TwoDown,
+        };
+        Move{coord: first, move_type, new_board_type: self.new_board_type}
+    }
+
+    pub fn extensions(&self) -> Squares {
+        let (dir, other_dir, count) = match self.move_type {
+            MoveType::Single => return Squares::default(),
+            MoveType::OneUp => (Direction::Up, None, 1),
+            MoveType::OneDown => (Direction::Down, None, 1),
+            MoveType::OneLeft => (Direction::Left, None, 1),
+            MoveType::OneRight => (Direction::Right, None, 1),
+            MoveType::TwoUp => (Direction::Up, None, 2),
+            MoveType::TwoDown => (Direction::Down, None, 2),
+            MoveType::TwoLeft => (Direction::Left, None, 2),
+            MoveType::TwoRight => (Direction::Right, None, 2),
+            MoveType::UpAndDown => (Direction::Up, Some(Direction::Down), 1),
+            MoveType::LeftAndRight => (Direction::Left, Some(Direction::Right), 1),
+        };
+        let mut squares = Squares::default();
+        let steps = (1..count + 1).map(|delta| (dir, delta)).chain(other_dir.map(|other_dir| (other_dir, 1)));
+        // Squares off the board are left out.
+        for (dir, delta) in steps {
+            if let Some(c) = self.coord.move_in(dir, delta).filter(|c| c.on_board()) {
+                squares.push(c);
+            }
+        }
+        squares
+    }
+}
+
+// The (at most three) squares of a move, stored inline so that generating moves doesn't allocate.
+#[derive(Copy, Clone, Debug)]
+pub struct Squares {
+    squares: [Coordinate; 3],
+    len: usize,
+}
+
+impl Squares {
+    fn push(&mut self, c: Coordinate) {
+        self.squares[self.len] = c;
+        self.len += 1;
+    }
+}
+
+impl Default for Squares {
+    fn default() -> Squares {
+        Squares {
+            squares: [Coordinate{row: 0, col: 0}; 3],
+            len: 0,
+        }
+    }
+}
+
+impl Deref for Squares {
+    type Target = [Coordinate];
+
+    fn deref(&self) -> &[Coordinate] {
+        &self.squares[..self.len]
+    }
+}
+
+impl Move {
+    // Compact notation used in game records, such as "OneRight@3,0" or "Single@2,1=Middle".
+    pub fn notation(&self) -> String {
+        let mut s = format!("{:?}@{},{}", self.move_type, self.coord.row, self.coord.col);
+        if let Some(bt) = self.new_board_type {
+            s.push_str(&format!("={:?}", bt));
+        }
+        s
+    }
+}
+
+// Parses the notation produced by `Move::notation`.
+// The move is not checked against the board, so it may even run off it;
+// `Board::check_move` reports why.
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Move, String> {
+        let (s, new_board_type) = match s.find('=') {
+            Some(i) => (&s[..i], Some(s[i + 1..].parse()?)),
+            None => (s, None),
+        };
+        let at = s.find('@').ok_or_else(|| format!("Expected MOVETYPE@ROW,COL, got {}", s))?;
+        let move_type = s[..at].parse()?;
+        let coords: Vec<_> = s[at + 1..].split(',').map(|x| x.trim().parse::<usize>()).collect();
+        let coord = match coords.as_slice() {
+            [Ok(row), Ok(col)] => Coordinate{row: *row, col: *col},
+            _ => return Err(format!("Invalid coordinate {}", &s[at + 1..])),
+        };
+        Ok(Move{coord, move_type, new_board_type})
     }
 }
 
@@ -143,3 +259,30 @@ impl Display for Move {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Coordinate,Move,MoveType};
+
+    #[test]
+    fn canonical_moves_start_at_top_left() {
+        let left = Move::new(Coordinate { row: 3, col: 3 }, MoveType::TwoLeft).unwrap();
+        let both = Move::new(Coordinate { row: 3, col: 2 }, MoveType::LeftAndRight).unwrap();
+        let right = Move::new(Coordinate { row: 3, col: 1 }, MoveType::TwoRight).unwrap();
+        assert_eq!(left.canonical(), right);
+        assert_eq!(both.canonical(), right);
+        let up = Move::new(Coordinate { row: 2, col: 4 }, MoveType::OneUp).unwrap();
+        assert_eq!(up.canonical(), Move::new(Coordinate { row: 1, col: 4 }, MoveType::OneDown).unwrap());
+    }
+
+    #[test]
+    fn moves_off_the_board_keep_only_their_squares_on_it() {
+        let left: Move = "OneLeft@3,0".parse().unwrap();
+        assert!(!left.in_bounds());
+        assert_eq!(left.canonical(), left);
+        assert_eq!(*left.squares(), [Coordinate { row: 3, col: 0 }]);
+        let far: Move = "TwoDown@9,9".parse().unwrap();
+        assert_eq!(far.canonical(), far);
+        assert!(far.squares().is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+// Agents choose moves, each in its own way, so that any two can play each other.
+//
+// The game is the same for both players, so an agent only needs the board to choose a move.
+
+use action::Move;
+use board::Board;
+use engine::Engine;
+use game::Game;
+use heuristic::{AlphaBeta, Weights};
+use mcts::{self, Mcts};
+use player::Player;
+use random::Rng;
+use solver::{self, Limits, Outcome, Search, Solver};
+use std::io::BufRead;
+use std::time::Duration;
+
+pub trait Agent {
+    // The move to play, or None if there are no moves, or the agent gives up.
+    fn choose(&mut self, board: &Board) -> Option<Move>;
+}
+
+// Plays a game from `board` between two agents, `one` playing Player::One and `two` Player::Two,
+// until a player has no move or gives up. The game's outcome is None if someone gave up.
+pub fn play_game(board: Board, first_player: Player, one: &mut dyn Agent, two: &mut dyn Agent) -> Game {
+    let mut game = Game::new(board, first_player);
+    while game.outcome().is_none() {
+        let agent: &mut dyn Agent = match game.player() {
+            Player::One => &mut *one,
+            Player::Two => &mut *two,
+        };
+        match agent.choose(game.board()) {
+            Some(m) => game.play(m).expect("agents choose legal moves"),
+            None => break,
+        }
+    }
+    game
+}
+
+// Plays perfectly: a winning move if there is one, and otherwise the best swindle.
+// Past its limits, which by default never come, it plays the first legal move instead,
+// or the first it couldn't rank if it knows the position is lost but not the best swindle.
+#[derive(Default)]
+pub struct PerfectAgent {
+    solver: Solver,
+    limits: Limits,
+}
+
+impl PerfectAgent {
+    pub fn new(limits: Limits) -> PerfectAgent {
+        PerfectAgent { solver: Solver::new(), limits }
+    }
+}
+
+impl Agent for PerfectAgent {
+    fn choose(&mut self, board: &Board) -> Option<Move> {
+        // Who is to move doesn't matter, as long as the position says the same player moves.
+        let position = board.position(Player::One);
+        // The limits are shared by finding the result and ranking the swindles.
+        self.solver.set_deadline(self.limits.deadline(self.solver.nodes()));
+        match self.solver.search(position) {
+            Outcome::Proven(_, Some(winning_move)) => Some(winning_move),
+            Outcome::Proven(_, None) => {
+                let (chances, unranked) = solver::practical_chances(&mut self.solver, position);
+                chances.first().map(|c| c.mv).or_else(|| unranked.first().cloned())
+            },
+            Outcome::Unknown(_) => position.legal_moves_iter().next(),
+        }
+    }
+}
+
+// Plays any legal move, all equally likely.
+pub struct RandomAgent {
+    rng: Rng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> RandomAgent {
+        RandomAgent { rng: Rng::new(seed) }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn choose(&mut self, board: &Board) -> Option<Move> {
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            None
+        } else {
+            Some(moves[self.rng.below(moves.len())])
+        }
+    }
+}
+
+// Plays the best move of a heuristic search, which stops at `limits` or after `time_limit`, whichever is sooner.
+pub struct HeuristicAgent {
+    search: AlphaBeta,
+    limits: Limits,
+}
+
+impl HeuristicAgent {
+    pub fn new(weights: Weights, limits: Limits, time_limit: Duration) -> HeuristicAgent {
+        HeuristicAgent { search: AlphaBeta::new(weights, time_limit), limits }
+    }
+}
+
+impl Agent for HeuristicAgent {
+    fn choose(&mut self, board: &Board) -> Option<Move> {
+        let position = board.position(Player::One);
+        self.search.set_deadline(self.limits.deadline(self.search.nodes()));
+        match self.search.search(position) {
+            Outcome::Proven(_, Some(m)) | Outcome::Unknown(Some(m)) => Some(m),
+            // Lost, or out of time before finishing even one move ahead.
+            Outcome::Proven(_, None) | Outcome::Unknown(None) => position.legal_moves_iter().next(),
+        }
+    }
+}
+
+// Plays the move Monte Carlo tree search suggests, searching until `budget` or `limits` runs out.
+pub struct MctsAgent {
+    mcts: Mcts,
+    budget: mcts::Budget,
+    limits: Limits,
+}
+
+impl MctsAgent {
+    pub fn new(seed: u64, budget: mcts::Budget, limits: Limits) -> MctsAgent {
+        MctsAgent { mcts: Mcts::new(seed), budget, limits }
+    }
+}
+
+impl Agent for MctsAgent {
+    fn choose(&mut self, board: &Board) -> Option<Move> {
+        self.mcts.set_deadline(self.limits.deadline(0));
+        self.mcts.search(&mut board.clone(), self.budget).best
+    }
+}
+
+impl Agent for Engine {
+    fn choose(&mut self, board: &Board) -> Option<Move> {
+        Engine::choose(self, board.position(Player::One))
+    }
+}
+
+// Asks a person for each move, showing the board and the numbered legal moves,
+// and reading a move number or a move in record notation (such as OneRight@3,0).
+// Reading `quit` or `q`, or reaching the end of the input, gives up.
+pub struct HumanAgent<R> {
+    input: R,
+}
+
+impl<R: BufRead> HumanAgent<R> {
+    pub fn new(input: R) -> HumanAgent<R> {
+        HumanAgent { input }
+    }
+}
+
+impl<R: BufRead> Agent for HumanAgent<R> {
+    fn choose(&mut self, board: &Board) -> Option<Move> {
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        println!("{}", board);
+        for (i, legal_move) in moves.iter().enumerate() {
+            println!("{} {}", i, legal_move);
+        }
+        loop {
+            println!("What move?");
+            let mut line = String::new();
+            if self.input.read_line(&mut line).expect("Failed to read line") == 0 {
+                return None;
+            }
+            let line = line.trim();
+            if line == "quit" || line == "q" {
+                return None;
+            }
+            match read_move(board, &moves, line) {
+                Ok(m) => return Some(m),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+}
+
+// The move a person typed: a number from the list of the board's legal `moves`,
+// or a move in record notation, checked against the board and put in canonical form.
+// Otherwise, a message saying what is wrong with it, to show them.
+pub fn read_move(board: &Board, moves: &[Move], input: &str) -> Result<Move, String> {
+    if let Ok(i) = input.parse::<usize>() {
+        return moves.get(i).cloned().ok_or_else(|| "Move not found.".to_string());
+    }
+    let m = input.parse::<Move>().map_err(|e| format!("Not a move number or move: {}.", e))?;
+    match board.check_move(m) {
+        Ok(()) => Ok(m.canonical()),
+        Err(e) => Err(format!("Illegal move: {}.", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{play_game, read_move, Agent, HeuristicAgent, HumanAgent, MctsAgent, PerfectAgent, RandomAgent};
+    use board::{self, Board};
+    use heuristic::Weights;
+    use mcts::Budget;
+    use player::Player;
+    use solver::Limits;
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    fn board(position: &str) -> Board {
+        let (array, board_type) = board::parse_position(position).unwrap();
+        Board::new(array, board_type)
+    }
+
+    #[test]
+    fn perfect_beats_random() {
+        let start = board(".###./...#./...#./.....");
+        let mut perfect = PerfectAgent::new(Limits::default());
+        for seed in 0..5 {
+            let game = play_game(start.clone(), Player::One, &mut perfect, &mut RandomAgent::new(seed));
+            assert_eq!(game.outcome(), Some(Player::One));
+        }
+    }
+
+    #[test]
+    fn same_seeds_same_game() {
+        let start = board(".###./...#./...#./.....");
+        let a = play_game(start.clone(), Player::One, &mut RandomAgent::new(1), &mut RandomAgent::new(2));
+        let b = play_game(start.clone(), Player::One, &mut RandomAgent::new(1), &mut RandomAgent::new(2));
+        assert_eq!(a.history(), b.history());
+        assert!(a.outcome().is_some());
+    }
+
+    #[test]
+    fn every_agent_plays_legal_moves() {
+        let start = board(".###./...#./...#./.....");
+        let moves = start.legal_moves();
+        let mut agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(PerfectAgent::new(Limits::default())),
+            Box::new(RandomAgent::new(0)),
+            Box::new(HeuristicAgent::new(Weights::default(), Limits::default(), Duration::from_millis(100))),
+            Box::new(MctsAgent::new(0, Budget::Iterations(100), Limits::default())),
+        ];
+        for agent in agents.iter_mut() {
+            assert!(moves.contains(&agent.choose(&start).unwrap()));
+        }
+    }
+
+    #[test]
+    fn humans_choose_by_number_or_notation() {
+        let start = board(".###./...#./...#./.....");
+        let moves = start.legal_moves();
+        let mut human = HumanAgent::new(Cursor::new("99\nnonsense\n3\nSingle@0,4\nq\n"));
+        assert_eq!(human.choose(&start), Some(moves[3]));
+        assert_eq!(human.choose(&start), Some("Single@0,4".parse().unwrap()));
+        assert_eq!(human.choose(&start), None);
+        assert_eq!(human.choose(&start), None);
+    }
+
+    #[test]
+    fn typed_moves_are_read_by_number_or_notation() {
+        let start = board(".###./...#./...#./.....");
+        let moves = start.legal_moves();
+        assert_eq!(read_move(&start, &moves, "2"), Ok(moves[2]));
+        assert_eq!(read_move(&start, &moves, "OneLeft@3,3"), Ok("OneRight@3,2".parse().unwrap()));
+        assert_eq!(read_move(&start, &moves, "99"), Err("Move not found.".to_string()));
+        assert!(read_move(&start, &moves, "OneLeft@3,0").unwrap_err().starts_with("Illegal move"));
+        assert!(read_move(&start, &moves, "nonsense").unwrap_err().starts_with("Not a move number or move"));
+    }
+
+    #[test]
+    fn limited_agents_still_play_legal_moves() {
+        let start = board(".###./...#./...#./.....");
+        let limits = Limits { time: None, nodes: Some(10) };
+        let mut perfect = PerfectAgent::new(limits);
+        let mut heuristic = HeuristicAgent::new(Weights::default(), limits, Duration::from_secs(60));
+        assert!(start.legal_moves().contains(&perfect.choose(&start).unwrap()));
+        assert!(start.legal_moves().contains(&heuristic.choose(&start).unwrap()));
+    }
+
+    #[test]
+    fn giving_up_ends_the_game_unfinished() {
+        let start = board(".###./...#./...#./.....");
+        let game = play_game(start, Player::One, &mut RandomAgent::new(0), &mut HumanAgent::new(Cursor::new("")));
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.history().len(), 1);
+    }
+}
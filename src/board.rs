@@ -1,9 +1,20 @@
-use std::collections::BTreeSet;
 use std::fmt::{Display, Error, Formatter};
-use action::{POSSIBLE_DIRECTIONS,POSSIBLE_MOVE_TYPES,Coordinate,Move,MoveEffect};
+use std::str::FromStr;
+use action::{POSSIBLE_DIRECTIONS,POSSIBLE_MOVE_TYPES,Coordinate,Move,MoveEffect,MoveType};
+use player::Player;
 
 pub const NUM_COLS: usize = 5;
 pub const NUM_ROWS: usize = 4;
+const CELLS: usize = NUM_ROWS * NUM_COLS;
+
+// The move types of moves in canonical form; see `Move::canonical`.
+const CANONICAL_MOVE_TYPES: [MoveType; 5] = [
+    MoveType::Single,
+    MoveType::OneRight,
+    MoveType::OneDown,
+    MoveType::TwoRight,
+    MoveType::TwoDown,
+];
 
 // Hacks for the three states of the lower-left of the board in JunSeok vs YeonSeung game
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Debug)]
@@ -67,6 +78,16 @@ impl BoardType {
     }
 }
 
+impl FromStr for BoardType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BoardType, String> {
+        POSSIBLE_BOARD_TYPES.iter().cloned().find(|bt| {
+            format!("{:?}", bt).eq_ignore_ascii_case(s)
+        }).ok_or_else(|| format!("Unknown board type {}", s))
+    }
+}
+
 #[derive(Copy, Clone)]
 enum Orientation {
     UpDown,
@@ -78,6 +99,7 @@ enum Orientation {
 }
 
 impl Orientation {
+    #[allow(clippy::wrong_self_convention)]
     fn to_str(&self) -> &'static str {
         match *self {
             Orientation::UpDown => "║",
@@ -158,25 +180,220 @@ const POSSIBLE_BOARD_TYPES: [BoardType; 5] = [
     BoardType::Right,
 ];
 
+// This assesses whether a coordinate can be placed on a board of the given type.
+fn compatible_with(board_type: Option<BoardType>, c: Coordinate) -> bool {
+    // Not in the lower left, so it's a free pass.
+    if !c.induces_board_type() {
+        return true;
+    }
+
+    match board_type {
+        Some(BoardType::Left)          => c != Coordinate{row: 2, col: 1} && c != Coordinate{row: 1, col: 1},
+        Some(BoardType::LeftOrMiddle)  => c != Coordinate{row: 1, col: 1},
+        Some(BoardType::Middle)        => c != Coordinate{row: 3, col: 0} && c != Coordinate{row: 1, col: 1},
+        Some(BoardType::RightOrMiddle) => c != Coordinate{row: 3, col: 0},
+        Some(BoardType::Right)         => c != Coordinate{row: 3, col: 0} && c != Coordinate{row: 2, col: 0},
+        None => true,
+    }
+}
+
+// Names the final board types whose railroad runs through a coordinate, such as "Middle or Right".
+fn arrangements_including(c: Coordinate) -> String {
+    let names: Vec<_> = POSSIBLE_BOARD_TYPES.iter()
+        .filter(|bt| bt.is_final() && compatible_with(Some(**bt), c))
+        .map(|bt| format!("{:?}", bt))
+        .collect();
+    names.join(" or ")
+}
+
+// Why a position given to `Board::try_new` can't arise in a game.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InvalidPosition {
+    NoTrack,
+    // The square's track isn't connected to the track in the top left.
+    Disconnected(Coordinate),
+    // The square has track, which the board type rules out or should have been narrowed by.
+    ArrangementMismatch(Coordinate, Option<BoardType>),
+    // The board type is narrower than unknown, but no moves placing the track in the lower left could have chosen it.
+    ArrangementUnchosen(BoardType),
+    // No arrangement allowed by the board type has a railroad through all the track.
+    NoLoop,
+    Finished,
+}
+
+impl Display for InvalidPosition {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            InvalidPosition::NoTrack => write!(formatter, "The board has no track"),
+            InvalidPosition::Disconnected(c) => write!(formatter, "The track at {} is not connected to the rest", c),
+            InvalidPosition::ArrangementMismatch(c, None) => write!(formatter, "{} has track, so the board type should be known", c),
+            InvalidPosition::ArrangementMismatch(c, Some(bt)) => write!(formatter, "{} has track, which does not agree with board type {:?}", c, bt),
+            InvalidPosition::ArrangementUnchosen(bt) => write!(formatter, "The board type is {:?}, which the track in the lower left could not have chosen", bt),
+            InvalidPosition::NoLoop => write!(formatter, "No arrangement has a railroad through all of the track"),
+            InvalidPosition::Finished => write!(formatter, "The railroad is already complete"),
+        }
+    }
+}
+
+// Why a move can't be made.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IllegalMove {
+    Occupied(Coordinate),
+    OutOfBounds,
+    NotAdjacent,
+    // The square isn't part of the railroad under the board's current type.
+    IncompatibleSquare(Coordinate, BoardType),
+    // The square isn't part of the railroad under the board type the move chooses.
+    IncompatibleChoice(Coordinate, BoardType),
+    // The move can't take the board from the first board type to the second.
+    IncompatibleBoardType(Option<BoardType>, Option<BoardType>),
+    GameOver,
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            IllegalMove::Occupied(c) => write!(formatter, "{} already has track", c),
+            IllegalMove::OutOfBounds => write!(formatter, "The move runs off the board"),
+            IllegalMove::NotAdjacent => write!(formatter, "The move is not next to any track"),
+            IllegalMove::IncompatibleSquare(c, bt) => write!(formatter, "{} requires {} but board is {:?}", c, arrangements_including(c), bt),
+            IllegalMove::IncompatibleChoice(c, bt) => write!(formatter, "{} requires {} but the move chooses {:?}", c, arrangements_including(c), bt),
+            IllegalMove::IncompatibleBoardType(_, None) => write!(formatter, "The move must choose a board type"),
+            IllegalMove::IncompatibleBoardType(None, Some(new)) => write!(formatter, "The move cannot make the board type {:?}", new),
+            IllegalMove::IncompatibleBoardType(Some(current), Some(new)) => write!(formatter, "The move cannot change the board type from {:?} to {:?}", current, new),
+            IllegalMove::GameOver => write!(formatter, "The game is over"),
+        }
+    }
+}
+
 pub type BoardArray = [[bool; NUM_COLS]; NUM_ROWS];
+
+// Parses a position written row by row from the top, rows separated by '/',
+// with '#' for track and '.' for an empty space.
+// The board type may follow after a ':', for example ".###./...#./...#./.....:LeftOrMiddle".
+pub fn parse_position(s: &str) -> Result<(BoardArray, Option<BoardType>), String> {
+    let (rows, board_type) = match s.find(':') {
+        Some(i) => (&s[..i], Some(s[i + 1..].parse()?)),
+        None => (s, None),
+    };
+
+    let rows: Vec<_> = rows.split('/').collect();
+    if rows.len() != NUM_ROWS {
+        return Err(format!("Expected {} rows, got {}", NUM_ROWS, rows.len()));
+    }
+
+    let mut array = [[false; NUM_COLS]; NUM_ROWS];
+    for (i, row) in rows.iter().enumerate() {
+        if row.chars().count() != NUM_COLS {
+            return Err(format!("Expected {} columns in row {}, got {}", NUM_COLS, i, row.chars().count()));
+        }
+        for (j, chr) in row.chars().enumerate() {
+            array[i][j] = match chr {
+                '#' => true,
+                '.' => false,
+                _ => return Err(format!("Unexpected {} at (row {}, col {})", chr, i, j)),
+            };
+        }
+    }
+    Ok((array, board_type))
+}
+
+#[derive(Clone)]
 pub struct Board {
     board: BoardArray,
     board_type: Option<BoardType>,
+    // The empty squares next to track that can be part of the railroad,
+    // kept up to date by `make_move` and `undo_move`.
+    frontier: BoardArray,
     moves_made: Vec<(Move, MoveEffect)>,
 }
 
+// Writes a position in the notation read by `parse_position`.
+pub fn format_position(array: &BoardArray, board_type: Option<BoardType>) -> String {
+    let rows: Vec<String> = array.iter().map(|row| {
+        row.iter().map(|&occupied| if occupied { '#' } else { '.' }).collect()
+    }).collect();
+    let mut s = rows.join("/");
+    if let Some(bt) = board_type {
+        s.push_str(&format!(":{:?}", bt));
+    }
+    s
+}
+
 impl Board {
     pub fn new(array: BoardArray, board_type: Option<BoardType>) -> Board {
         Board {
             board: array,
-            board_type: board_type,
+            board_type,
+            frontier: scan_frontier(&array, board_type),
             moves_made: Vec::new(),
         }
     }
 
+    // Like `new`, but checks that the position could arise in a game:
+    // the track is connected, lies on the railroad of some arrangement,
+    // agrees with the board type (which only track in the lower left can narrow), and is not yet complete.
+    pub fn try_new(array: BoardArray, board_type: Option<BoardType>) -> Result<Board, InvalidPosition> {
+        let occupied: Vec<_> = (0..NUM_ROWS).flat_map(|row| (0..NUM_COLS).map(move |col| Coordinate{row, col}))
+            .filter(|c| array[c.row][c.col]).collect();
+
+        let start = *occupied.first().ok_or(InvalidPosition::NoTrack)?;
+        let mut connected = [[false; NUM_COLS]; NUM_ROWS];
+        connected[start.row][start.col] = true;
+        let mut to_visit = vec![start];
+        while let Some(c) = to_visit.pop() {
+            for dir in POSSIBLE_DIRECTIONS.iter() {
+                if let Some(n) = c.move_in(*dir, 1) {
+                    if array[n.row][n.col] && !connected[n.row][n.col] {
+                        connected[n.row][n.col] = true;
+                        to_visit.push(n);
+                    }
+                }
+            }
+        }
+        if let Some(&c) = occupied.iter().find(|c| !connected[c.row][c.col]) {
+            return Err(InvalidPosition::Disconnected(c));
+        }
+
+        // Track in the lower left narrows the board type, as in `legal_moves`.
+        for &c in occupied.iter().filter(|c| c.induces_board_type()) {
+            let agrees = match board_type {
+                None => false,
+                Some(bt) if bt.is_final() => compatible_with(board_type, c),
+                Some(bt) => bt.induced_by(c),
+            };
+            if !agrees {
+                return Err(InvalidPosition::ArrangementMismatch(c, board_type));
+            }
+        }
+        // And only track there narrows it, to a board type that the moves placing it could have chosen.
+        if let Some(bt) = board_type {
+            let lower_left: Vec<_> = occupied.iter().cloned().filter(|c| c.induces_board_type()).collect();
+            if !board_types_choosable(&lower_left).contains(bt) {
+                return Err(InvalidPosition::ArrangementUnchosen(bt));
+            }
+        }
+
+        let on_some_loop = POSSIBLE_BOARD_TYPES.iter().any(|bt| {
+            bt.is_final() && bt.applies_to(board_type) && occupied.iter().all(|&c| compatible_with(Some(*bt), c))
+        });
+        if !on_some_loop {
+            return Err(InvalidPosition::NoLoop);
+        }
+
+        let board = Board::new(array, board_type);
+        if board.legal_moves_iter().next().is_none() {
+            return Err(InvalidPosition::Finished);
+        }
+        Ok(board)
+    }
+
+    // Makes a move without checking it; it should come from `legal_moves`.
+    // Use `try_make_move` for moves from elsewhere.
     pub fn make_move(&mut self, m: Move) {
         let effect = MoveEffect {
             old_board_type: self.board_type,
+            old_frontier: self.frontier,
         };
         self.moves_made.push((m, effect));
         if let Some(bt) = m.new_board_type {
@@ -185,26 +402,56 @@ impl Board {
             }
             self.board_type = m.new_board_type
         }
-        self.set_squares(m, true)
+        self.set_squares(m, true);
+        debug_assert!(self.frontier_is_current(), "frontier out of date after {}", m);
     }
 
     pub fn undo_move(&mut self) -> Option<Move> {
         if let Some((mov, effect)) = self.moves_made.pop() {
             self.board_type = effect.old_board_type;
             self.set_squares(mov, false);
+            self.frontier = effect.old_frontier;
+            debug_assert!(self.frontier_is_current(), "frontier out of date after undoing {}", mov);
             Some(mov)
         } else {
             None
         }
     }
 
-    fn set_squares(&mut self, m: Move, mode: bool) {
-        self.board[m.coord.row][m.coord.col] = mode;
-        for other_space in m.extensions().iter() {
-            self.board[other_space.row][other_space.col] = mode;
+    pub fn array(&self) -> BoardArray {
+        self.board
+    }
+
+    pub fn board_type(&self) -> Option<BoardType> {
+        self.board_type
+    }
+
+    pub fn moves_made(&self) -> Vec<Move> {
+        self.moves_made.iter().map(|&(m, _)| m).collect()
+    }
+
+    // The number of moves made, without copying them as `moves_made` does.
+    pub fn num_moves(&self) -> usize {
+        self.moves_made.len()
+    }
+
+    // Places (or removes) the squares of a move.
+    // When placing, the frontier is updated from the squares around the move;
+    // when removing, the caller restores the frontier saved in the move's effect.
+    fn set_squares(&mut self, m: Move, place: bool) {
+        for c in m.squares().iter() {
+            self.board[c.row][c.col] = place;
+        }
+        if place {
+            update_frontier(&mut self.frontier, &self.board, self.board_type, m);
         }
     }
 
+    // Whether the incrementally maintained frontier matches a full rescan.
+    fn frontier_is_current(&self) -> bool {
+        self.frontier == scan_frontier(&self.board, self.board_type)
+    }
+
     fn occupied(&self, c: Coordinate) -> bool {
         self.board[c.row][c.col]
     }
@@ -212,98 +459,536 @@ impl Board {
     // This assesses whether a coordinate can be placed on the board,
     // given the current type of the board.
     fn compatible(&self, c: Coordinate) -> bool {
-        // Not in the lower left, so it's a free pass.
-        if !c.induces_board_type() {
-            return true;
+        compatible_with(self.board_type, c)
+    }
+
+    fn board_type_final(&self) -> bool {
+        self.board_type.is_some_and(|x| x.is_final())
+    }
+
+    // Checks a move against the rules, which accept any move placing the same
+    // squares as a move from `legal_moves`, and explains why an illegal move is illegal.
+    pub fn check_move(&self, m: Move) -> Result<(), IllegalMove> {
+        if self.legal_moves_iter().next().is_none() {
+            return Err(IllegalMove::GameOver);
+        }
+        if !m.in_bounds() {
+            return Err(IllegalMove::OutOfBounds);
         }
 
-        match self.board_type {
-            Some(BoardType::Left)          => c != Coordinate{row: 2, col: 1} && c != Coordinate{row: 1, col: 1},
-            Some(BoardType::LeftOrMiddle)  => c != Coordinate{row: 1, col: 1},
-            Some(BoardType::Middle)        => c != Coordinate{row: 3, col: 0} && c != Coordinate{row: 1, col: 1},
-            Some(BoardType::RightOrMiddle) => c != Coordinate{row: 3, col: 0},
-            Some(BoardType::Right)         => c != Coordinate{row: 3, col: 0} && c != Coordinate{row: 2, col: 0},
-            None => true,
+        let squares = m.squares();
+        if let Some(&c) = squares.iter().find(|&&c| self.occupied(c)) {
+            return Err(IllegalMove::Occupied(c));
         }
+        if let (Some(&c), Some(bt)) = (squares.iter().find(|&&c| !self.compatible(c)), self.board_type) {
+            return Err(IllegalMove::IncompatibleSquare(c, bt));
+        }
+        if !squares.iter().any(|&c| adjacent_to_track(&self.board, c)) {
+            return Err(IllegalMove::NotAdjacent);
+        }
+
+        let induces_board_type = squares.iter().any(|c| c.induces_board_type());
+        let ok = match m.new_board_type {
+            Some(bt) if induces_board_type && !self.board_type_final() => {
+                if let Some(&c) = squares.iter().find(|&&c| !bt.induced_by(c)) {
+                    return Err(IllegalMove::IncompatibleChoice(c, bt));
+                }
+                board_types_for(self.board_type, m).contains(bt)
+            },
+            None => !induces_board_type || self.board_type_final(),
+            Some(_) => false,
+        };
+        if !ok {
+            return Err(IllegalMove::IncompatibleBoardType(self.board_type, m.new_board_type));
+        }
+        Ok(())
+    }
+
+    // Makes a move if it is legal, and otherwise leaves the board unchanged.
+    pub fn try_make_move(&mut self, m: Move) -> Result<(), IllegalMove> {
+        self.check_move(m)?;
+        self.make_move(m);
+        Ok(())
+    }
+
+    // Each legal move, written in canonical form.
+    // Moves placing the same squares with the same board type are only listed once.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.legal_moves_iter().collect()
+    }
+
+    // Like `legal_moves`, but generates the moves as they are needed.
+    pub fn legal_moves_iter(&self) -> LegalMoves {
+        LegalMoves::new(self.board, self.board_type, self.frontier)
     }
 
-    fn frontier(&self) -> Vec<Coordinate> {
-        let mut results = Vec::new();
+    // Every position one legal move before this one, with that move.
+    // A move choosing this board's type may have been made from any wider type,
+    // and a move not choosing one from a position of the same type.
+    // The earlier positions have no history of their own.
+    pub fn predecessors(&self) -> Vec<(Board, Move)> {
+        let earlier_board_types: Vec<_> = Some(None).into_iter()
+            .chain(POSSIBLE_BOARD_TYPES.iter().map(|bt| Some(*bt)))
+            .collect();
+        let mut predecessors = Vec::new();
         for row in 0..NUM_ROWS {
             for col in 0..NUM_COLS {
-                let coord = Coordinate{row: row, col: col};
-                if self.occupied(coord) || !self.compatible(coord) {
-                    continue;
+                for &move_type in CANONICAL_MOVE_TYPES.iter() {
+                    let m = match Move::new(Coordinate{row, col}, move_type) {
+                        Some(m) => m,
+                        None => continue,
+                    };
+                    if !m.squares().iter().all(|&c| self.occupied(c)) {
+                        continue;
+                    }
+                    let mut array = self.board;
+                    for c in m.squares().iter() {
+                        array[c.row][c.col] = false;
+                    }
+                    // Until track is placed in the lower left, nothing chooses a board type.
+                    let chose_board_type = (0..NUM_ROWS).any(|row| (0..NUM_COLS).any(|col| {
+                        array[row][col] && Coordinate{row, col}.induces_board_type()
+                    }));
+
+                    for &board_type in earlier_board_types.iter() {
+                        if board_type.is_some() != chose_board_type {
+                            continue;
+                        }
+                        let earlier = match Board::try_new(array, board_type) {
+                            Ok(board) => board,
+                            Err(_) => continue,
+                        };
+                        let candidates = [Some(m), self.board_type.map(|bt| m.with_board_type(bt))];
+                        for &candidate in candidates.iter().flatten() {
+                            let narrows = candidate.new_board_type.is_some();
+                            if (narrows || board_type == self.board_type) && earlier.legal_moves_iter().any(|x| x == candidate) {
+                                predecessors.push((earlier.clone(), candidate));
+                            }
+                        }
+                    }
                 }
-                let have_neighbor = POSSIBLE_DIRECTIONS.iter().any(|dir| {
-                    coord.move_in(*dir, 1).map_or(false, |x| self.occupied(x))
-                });
-                if have_neighbor {
-                    results.push(coord);
+            }
+        }
+        predecessors
+    }
+
+    // The current position, with `to_move` to move.
+    pub fn position(&self, to_move: Player) -> Position {
+        Position { board: self.board, board_type: self.board_type, to_move, frontier: self.frontier }
+    }
+}
+
+// A position on its own, without the history a `Board` keeps for undoing moves.
+// Being small and `Copy`, positions can be stored in maps or sent between threads;
+// searches make a new position for each move instead of undoing.
+// Like a board, a position carries its frontier, updating it as moves are applied.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Position {
+    board: BoardArray,
+    board_type: Option<BoardType>,
+    to_move: Player,
+    // Follows from the fields above, so it doesn't change how positions compare.
+    frontier: BoardArray,
+}
+
+impl Position {
+    pub fn new(array: BoardArray, board_type: Option<BoardType>, to_move: Player) -> Position {
+        Position { board: array, board_type, to_move, frontier: scan_frontier(&array, board_type) }
+    }
+
+    pub fn array(&self) -> BoardArray {
+        self.board
+    }
+
+    pub fn board_type(&self) -> Option<BoardType> {
+        self.board_type
+    }
+
+    pub fn to_move(&self) -> Player {
+        self.to_move
+    }
+
+    // Packs the position into 24 bits: the track in row-major order from bit 0,
+    // then the board type (0 for none, otherwise 1 more than its index in POSSIBLE_BOARD_TYPES),
+    // then 1 if the second player is to move.
+    pub fn encode(&self) -> u32 {
+        let mut code = 0;
+        for row in 0..NUM_ROWS {
+            for col in 0..NUM_COLS {
+                if self.board[row][col] {
+                    code |= 1 << (row * NUM_COLS + col);
                 }
             }
         }
-        results
+        let board_type = match self.board_type {
+            Some(bt) => 1 + POSSIBLE_BOARD_TYPES.iter().position(|x| *x == bt).expect("every board type is possible"),
+            None => 0,
+        };
+        code |= (board_type as u32) << CELLS;
+        if self.to_move == Player::Two {
+            code |= 1 << (CELLS + 3);
+        }
+        code
     }
 
-    fn board_type_final(&self) -> bool {
-        self.board_type.map_or(false, |x| x.is_final())
+    // Unpacks a position packed by `encode`.
+    pub fn decode(code: u32) -> Option<Position> {
+        if code >> (CELLS + 4) != 0 {
+            return None;
+        }
+        let mut board = [[false; NUM_COLS]; NUM_ROWS];
+        for (row, cells) in board.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                *cell = code & (1 << (row * NUM_COLS + col)) != 0;
+            }
+        }
+        let board_type = match (code >> CELLS) & 7 {
+            0 => None,
+            i => Some(*POSSIBLE_BOARD_TYPES.get(i as usize - 1)?),
+        };
+        let to_move = if code & (1 << (CELLS + 3)) != 0 { Player::Two } else { Player::One };
+        Some(Position::new(board, board_type, to_move))
+    }
+
+    // The position after a move, with the other player to move.
+    // Like `Board::make_move`, the move isn't checked; it should come from `legal_moves`.
+    pub fn apply(&self, m: Move) -> Position {
+        let mut board = self.board;
+        for c in m.squares().iter() {
+            board[c.row][c.col] = true;
+        }
+        let board_type = match m.new_board_type {
+            Some(bt) if !bt.applies_to(self.board_type) => {
+                panic!("Board type is {:?}, not compatible with {:?}", self.board_type, bt);
+            },
+            Some(bt) => Some(bt),
+            None => self.board_type,
+        };
+        let mut frontier = self.frontier;
+        update_frontier(&mut frontier, &board, board_type, m);
+        let position = Position { board, board_type, to_move: self.to_move.opponent(), frontier };
+        debug_assert!(position.frontier == scan_frontier(&board, board_type), "frontier out of date after {}", m);
+        position
     }
 
     pub fn legal_moves(&self) -> Vec<Move> {
-        let mut results = Vec::new();
-        for frontier_space in self.frontier().iter() {
-            for move_type in POSSIBLE_MOVE_TYPES.iter() {
-                let mov = match Move::new(*frontier_space, *move_type) {
-                    Some(x) => x,
-                    None => continue,
-                };
-                let mut other_space_taken = false;
-                let mut induces_board_type = frontier_space.induces_board_type();
-                for other_space in mov.extensions().iter() {
-                    if other_space.induces_board_type() {
-                        induces_board_type = true;
-                    }
-                    if self.occupied(*other_space) || !self.compatible(*other_space) {
-                        other_space_taken = true;
-                        break;
+        self.legal_moves_iter().collect()
+    }
+
+    pub fn legal_moves_iter(&self) -> LegalMoves {
+        LegalMoves::new(self.board, self.board_type, self.frontier)
+    }
+
+    // The number of empty squares next to track that can be part of the railroad.
+    pub fn frontier_size(&self) -> usize {
+        self.frontier.iter().map(|row| row.iter().filter(|&&x| x).count()).sum()
+    }
+
+    // The number of empty squares left on the railroad.
+    // Every arrangement's railroad is as long as the others, so it doesn't matter which
+    // of the arrangements still possible is counted.
+    pub fn squares_left(&self) -> usize {
+        let arrangement = POSSIBLE_BOARD_TYPES.iter().cloned()
+            .find(|bt| bt.is_final() && bt.applies_to(self.board_type))
+            .expect("some arrangement is still possible");
+        let mut count = 0;
+        for row in 0..NUM_ROWS {
+            for col in 0..NUM_COLS {
+                if !self.board[row][col] && compatible_with(Some(arrangement), Coordinate{row, col}) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // The least of the positions related to this one by a symmetry of the board.
+    // Related positions have the same result, so one of them can stand in for all.
+    pub fn canonical(&self) -> Position {
+        let mut canonical = *self;
+        for symmetry in SYMMETRIES.iter() {
+            if symmetry.preserves(self.board_type) {
+                let mut board = [[false; NUM_COLS]; NUM_ROWS];
+                let mut frontier = [[false; NUM_COLS]; NUM_ROWS];
+                for row in 0..NUM_ROWS {
+                    for col in 0..NUM_COLS {
+                        let c = symmetry.apply(Coordinate{row, col});
+                        board[c.row][c.col] = self.board[row][col];
+                        frontier[c.row][c.col] = self.frontier[row][col];
                     }
                 }
-                if other_space_taken {
-                    continue;
+                let image = Position { board, frontier, .. *self };
+                if image < canonical {
+                    canonical = image;
                 }
+            }
+        }
+        canonical
+    }
+}
 
-                if !induces_board_type || self.board_type_final() {
-                    results.push(mov);
-                    continue;
-                }
+// The ways a rectangular board maps onto itself, other than leaving it alone.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Symmetry {
+    // Top to bottom.
+    FlipRows,
+    // Left to right.
+    FlipCols,
+    // 180 degree rotation.
+    Rotate,
+}
+pub const SYMMETRIES: [Symmetry; 3] = [
+    Symmetry::FlipRows,
+    Symmetry::FlipCols,
+    Symmetry::Rotate,
+];
+
+impl Symmetry {
+    pub fn apply(&self, c: Coordinate) -> Coordinate {
+        match *self {
+            Symmetry::FlipRows => Coordinate{row: NUM_ROWS - 1 - c.row, col: c.col},
+            Symmetry::FlipCols => Coordinate{row: c.row, col: NUM_COLS - 1 - c.col},
+            Symmetry::Rotate => Coordinate{row: NUM_ROWS - 1 - c.row, col: NUM_COLS - 1 - c.col},
+        }
+    }
+
+    // Whether the symmetry leaves the rules unchanged on a board of the given type.
+    // Until the arrangement is settled, moves in the lower left choose between arrangements,
+    // which no symmetry preserves. Once it is settled, the rules only depend on which
+    // squares the railroad covers, so the symmetry must map those squares onto themselves.
+    pub fn preserves(&self, board_type: Option<BoardType>) -> bool {
+        match board_type {
+            Some(bt) if bt.is_final() => (0..NUM_ROWS).all(|row| (0..NUM_COLS).all(|col| {
+                let c = Coordinate{row, col};
+                compatible_with(board_type, c) == compatible_with(board_type, self.apply(c))
+            })),
+            _ => false,
+        }
+    }
+}
 
-                let mut ok_board_types: BTreeSet<_> = POSSIBLE_BOARD_TYPES.iter().cloned().filter(|board_type| {
-                    board_type.applies_to(self.board_type) &&
-                        board_type.induced_by(*frontier_space) &&
-                        mov.extensions().iter().all(|coord| board_type.induced_by(*coord))
-                }).collect();
+fn adjacent_to_track(board: &BoardArray, c: Coordinate) -> bool {
+    POSSIBLE_DIRECTIONS.iter().any(|dir| {
+        c.move_in(*dir, 1).is_some_and(|x| board[x.row][x.col])
+    })
+}
 
-                // Dominated board types...
-                if ok_board_types.contains(&BoardType::LeftOrMiddle) {
-                    ok_board_types.remove(&BoardType::Left);
-                    ok_board_types.remove(&BoardType::Middle);
+// The empty squares next to track that can be part of the railroad.
+// Updates the frontier of a board just after `m` was placed on it (and its board type narrowed by `m`),
+// looking only at the squares around the move.
+fn update_frontier(frontier: &mut BoardArray, board: &BoardArray, board_type: Option<BoardType>, m: Move) {
+    for c in m.squares().iter() {
+        frontier[c.row][c.col] = false;
+        for dir in POSSIBLE_DIRECTIONS.iter() {
+            if let Some(n) = c.move_in(*dir, 1) {
+                if !board[n.row][n.col] && compatible_with(board_type, n) {
+                    frontier[n.row][n.col] = true;
                 }
-                if ok_board_types.contains(&BoardType::RightOrMiddle) {
-                    ok_board_types.remove(&BoardType::Right);
-                    ok_board_types.remove(&BoardType::Middle);
+            }
+        }
+    }
+    // A narrower board type rules out some squares in the lower left.
+    if m.new_board_type.is_some() {
+        for (row, cells) in frontier.iter_mut().enumerate().skip(1) {
+            for (col, cell) in cells.iter_mut().enumerate().take(2) {
+                if !compatible_with(board_type, Coordinate{row, col}) {
+                    *cell = false;
+                }
+            }
+        }
+    }
+}
+
+fn scan_frontier(board: &BoardArray, board_type: Option<BoardType>) -> BoardArray {
+    let mut frontier = [[false; NUM_COLS]; NUM_ROWS];
+    for row in 0..NUM_ROWS {
+        for col in 0..NUM_COLS {
+            let coord = Coordinate{row, col};
+            frontier[row][col] = !board[row][col] && compatible_with(board_type, coord) && adjacent_to_track(board, coord);
+        }
+    }
+    frontier
+}
+
+// A set of board types, small enough to copy around while generating moves.
+#[derive(Copy, Clone, Default)]
+struct BoardTypeSet(u8);
+
+impl BoardTypeSet {
+    fn bit(bt: BoardType) -> u8 {
+        1 << POSSIBLE_BOARD_TYPES.iter().position(|x| *x == bt).expect("every board type is possible")
+    }
+
+    fn contains(&self, bt: BoardType) -> bool {
+        self.0 & BoardTypeSet::bit(bt) != 0
+    }
+
+    fn insert(&mut self, bt: BoardType) {
+        self.0 |= BoardTypeSet::bit(bt);
+    }
+
+    fn remove(&mut self, bt: BoardType) {
+        self.0 &= !BoardTypeSet::bit(bt);
+    }
+
+    // Removes and returns the first board type, in the order of POSSIBLE_BOARD_TYPES.
+    fn pop_first(&mut self) -> Option<BoardType> {
+        let first = POSSIBLE_BOARD_TYPES.iter().cloned().find(|bt| self.contains(*bt));
+        if let Some(bt) = first {
+            self.remove(bt);
+        }
+        first
+    }
+}
+
+// The board types a move in the lower left may choose.
+fn board_types_for(board_type: Option<BoardType>, mov: Move) -> BoardTypeSet {
+    let mut ok_board_types = BoardTypeSet::default();
+    for bt in POSSIBLE_BOARD_TYPES.iter() {
+        if bt.applies_to(board_type) && mov.squares().iter().all(|coord| bt.induced_by(*coord)) {
+            ok_board_types.insert(*bt);
+        }
+    }
+
+    // Dominated board types...
+    if ok_board_types.contains(BoardType::LeftOrMiddle) {
+        ok_board_types.remove(BoardType::Left);
+        ok_board_types.remove(BoardType::Middle);
+    }
+    if ok_board_types.contains(BoardType::RightOrMiddle) {
+        ok_board_types.remove(BoardType::Right);
+        ok_board_types.remove(BoardType::Middle);
+    }
+    ok_board_types
+}
+
+// The board types that moves placing exactly `squares`, all in the lower left, could have chosen
+// one after another, as `legal_moves` offers them. Track elsewhere doesn't narrow the board type,
+// so only the lower left squares of each move matter.
+fn board_types_choosable(squares: &[Coordinate]) -> BoardTypeSet {
+    let moves: Vec<_> = squares.iter().flat_map(|&c| POSSIBLE_MOVE_TYPES.iter().filter_map(move |&mt| Move::new(c, mt)))
+        .filter(|m| m.squares().iter().all(|s| squares.contains(s)))
+        .collect();
+    let bits = |m: &Move| m.squares().iter().fold(0u8, |bits, s| {
+        bits | 1 << squares.iter().position(|x| x == s).expect("the move's squares were checked")
+    });
+    let all = (1u8 << squares.len()) - 1;
+
+    // Each state is the squares placed so far, as bits of `squares`, and the board type then.
+    let mut choosable = BoardTypeSet::default();
+    let mut seen = vec![(0, None)];
+    let mut to_visit = vec![(0, None)];
+    while let Some((placed, board_type)) = to_visit.pop() {
+        if placed == all {
+            if let Some(bt) = board_type {
+                choosable.insert(bt);
+            }
+            continue;
+        }
+        for m in moves.iter().filter(|m| bits(m) & placed == 0) {
+            let mut chosen = board_types_for(board_type, *m);
+            while let Some(bt) = chosen.pop_first() {
+                let state = (placed | bits(m), Some(bt));
+                if !seen.contains(&state) {
+                    seen.push(state);
+                    to_visit.push(state);
                 }
+            }
+        }
+    }
+    choosable
+}
+
+// Generates legal moves one at a time, without allocating.
+// It keeps its own copy of the position, so the board may change while it is in use.
+pub struct LegalMoves {
+    board: BoardArray,
+    board_type: Option<BoardType>,
+    frontier: BoardArray,
+    // The next frontier space and move type to try, as indices.
+    cell: usize,
+    move_type: usize,
+    // A move still to be generated with each of these board types.
+    pending: Option<(Move, BoardTypeSet)>,
+}
 
-                for board_type in ok_board_types.iter() {
-                    results.push(mov.with_board_type(*board_type));
+impl LegalMoves {
+    fn new(board: BoardArray, board_type: Option<BoardType>, frontier: BoardArray) -> LegalMoves {
+        LegalMoves {
+            board,
+            board_type,
+            frontier,
+            cell: 0,
+            move_type: 0,
+            pending: None,
+        }
+    }
+
+    // The move `move_type` at `frontier_space` in canonical form,
+    // with the board types it may choose if it has to choose one.
+    fn candidate(&self, frontier_space: Coordinate, move_type: MoveType) -> Option<(Move, Option<BoardTypeSet>)> {
+        let mov = Move::new(frontier_space, move_type)?;
+        let squares = mov.squares();
+        // The same squares are reached from each of their frontier spaces,
+        // so only keep them when reached from the first.
+        let first_frontier_space = squares.iter().find(|c| self.frontier[c.row][c.col]);
+        if first_frontier_space != Some(&frontier_space) {
+            return None;
+        }
+        let mut induces_board_type = false;
+        for other_space in squares.iter() {
+            if other_space.induces_board_type() {
+                induces_board_type = true;
+            }
+            if self.board[other_space.row][other_space.col] || !compatible_with(self.board_type, *other_space) {
+                return None;
+            }
+        }
+
+        let mov = mov.canonical();
+        if !induces_board_type || self.board_type.is_some_and(|x| x.is_final()) {
+            Some((mov, None))
+        } else {
+            Some((mov, Some(board_types_for(self.board_type, mov))))
+        }
+    }
+}
+
+impl Iterator for LegalMoves {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            if let Some((mov, ref mut board_types)) = self.pending {
+                if let Some(bt) = board_types.pop_first() {
+                    return Some(mov.with_board_type(bt));
                 }
+                self.pending = None;
+            }
+
+            if self.cell >= CELLS {
+                return None;
+            }
+            let frontier_space = Coordinate{row: self.cell / NUM_COLS, col: self.cell % NUM_COLS};
+            if !self.frontier[frontier_space.row][frontier_space.col] || self.move_type >= POSSIBLE_MOVE_TYPES.len() {
+                self.cell += 1;
+                self.move_type = 0;
+                continue;
+            }
+            let move_type = POSSIBLE_MOVE_TYPES[self.move_type];
+            self.move_type += 1;
+
+            match self.candidate(frontier_space, move_type) {
+                Some((mov, None)) => return Some(mov),
+                Some((mov, Some(board_types))) => self.pending = Some((mov, board_types)),
+                None => (),
             }
         }
-        results
     }
 }
 
 impl Display for Board {
+    #[allow(deprecated)]
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
         // Print header row
         try!(formatter.write_str("    "));
@@ -348,9 +1033,12 @@ impl Display for Board {
 }
 
 #[cfg(test)]
+#[allow(clippy::manual_contains)]
 mod tests {
-    use super::{Board,BoardArray,BoardType};
-    use action::Coordinate;
+    use super::{format_position,parse_position,Board,BoardArray,BoardType,IllegalMove,InvalidPosition,Position,Symmetry,NUM_COLS,NUM_ROWS};
+    use action::{Coordinate,Move,MoveType};
+    use player::Player;
+    use std::collections::HashSet;
 
     const START_BOARD: BoardArray = [
         [false,  true,  true,  true, false],
@@ -620,4 +1308,367 @@ mod tests {
         let board = Board::new(FINISHED_RIGHT_BOARD, Some(BoardType::Right));
         assert!(board.legal_moves().is_empty());
     }
+
+    #[test]
+    fn parses_position() {
+        assert_eq!(parse_position(".###./...#./...#./....."), Ok((START_BOARD, None)));
+        let position = "####./#...#/..##./.....:leftormiddle";
+        assert_eq!(parse_position(position).map(|(_, bt)| bt), Ok(Some(BoardType::LeftOrMiddle)));
+    }
+
+    #[test]
+    fn formats_position() {
+        assert_eq!(format_position(&START_BOARD, None), ".###./...#./...#./.....");
+        let formatted = format_position(&LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle));
+        assert_eq!(parse_position(&formatted), Ok((LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle))));
+    }
+
+    #[test]
+    fn rejects_malformed_position() {
+        assert!(parse_position(".###./...#./...#.").is_err());
+        assert!(parse_position(".###./...#./...#./....").is_err());
+        assert!(parse_position(".###./...#./...#./..x..").is_err());
+        assert!(parse_position(".###./...#./...#./.....:Sideways").is_err());
+    }
+
+    fn mv(row: usize, col: usize, move_type: MoveType) -> Move {
+        Move::new(Coordinate { row, col }, move_type).unwrap()
+    }
+
+    #[test]
+    fn try_make_move_accepts_legal_move() {
+        let mut board = Board::new(START_BOARD, None);
+        assert_eq!(board.try_make_move(mv(3, 3, MoveType::OneLeft)), Ok(()));
+        assert_eq!(board.moves_made().len(), 1);
+    }
+
+    #[test]
+    fn try_make_move_accepts_move_with_adjacent_extension() {
+        let mut board = Board::new(START_BOARD, None);
+        assert_eq!(board.try_make_move(mv(3, 2, MoveType::OneRight)), Ok(()));
+    }
+
+    #[test]
+    fn try_make_move_rejects_occupied_square() {
+        let mut board = Board::new(START_BOARD, None);
+        let result = board.try_make_move(mv(0, 0, MoveType::OneRight));
+        assert_eq!(result, Err(IllegalMove::Occupied(Coordinate { row: 0, col: 1 })));
+        assert!(board.moves_made().is_empty());
+        assert_eq!(board.array(), START_BOARD);
+    }
+
+    #[test]
+    fn try_make_move_rejects_out_of_bounds() {
+        let mut board = Board::new(START_BOARD, None);
+        let mut m = mv(3, 3, MoveType::Single);
+        m.coord = Coordinate { row: NUM_ROWS, col: 0 };
+        assert_eq!(board.try_make_move(m), Err(IllegalMove::OutOfBounds));
+        let m = "OneLeft@3,0".parse().unwrap();
+        assert_eq!(board.try_make_move(m), Err(IllegalMove::OutOfBounds));
+    }
+
+    #[test]
+    fn try_make_move_rejects_non_adjacent() {
+        let mut board = Board::new(START_BOARD, None);
+        assert_eq!(board.try_make_move(mv(3, 0, MoveType::Single)), Err(IllegalMove::NotAdjacent));
+    }
+
+    #[test]
+    fn try_make_move_rejects_incompatible_square() {
+        let mut board = Board::new(LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle));
+        let result = board.try_make_move(mv(1, 1, MoveType::Single));
+        assert_eq!(result, Err(IllegalMove::IncompatibleSquare(Coordinate { row: 1, col: 1 }, BoardType::LeftOrMiddle)));
+    }
+
+    #[test]
+    fn try_make_move_rejects_incompatible_board_type() {
+        let mut board = Board::new(START_BOARD, None);
+        let missing = mv(1, 1, MoveType::OneRight);
+        assert_eq!(board.try_make_move(missing), Err(IllegalMove::IncompatibleBoardType(None, None)));
+        let wrong = missing.with_board_type(BoardType::Left);
+        assert_eq!(board.try_make_move(wrong), Err(IllegalMove::IncompatibleChoice(Coordinate { row: 1, col: 1 }, BoardType::Left)));
+        // Left is dominated by LeftOrMiddle here.
+        let dominated = mv(1, 0, MoveType::OneUp).with_board_type(BoardType::Left);
+        assert_eq!(board.try_make_move(dominated), Err(IllegalMove::IncompatibleBoardType(None, Some(BoardType::Left))));
+        assert_eq!(board.try_make_move(missing.with_board_type(BoardType::Right)), Ok(()));
+    }
+
+    #[test]
+    fn try_make_move_rejects_move_after_game_over() {
+        let mut board = Board::new(FINISHED_LEFT_BOARD, Some(BoardType::Left));
+        assert_eq!(board.try_make_move(mv(1, 1, MoveType::Single)), Err(IllegalMove::GameOver));
+    }
+
+    #[test]
+    fn try_new_accepts_valid_positions() {
+        assert!(Board::try_new(START_BOARD, None).is_ok());
+        assert!(Board::try_new(LEFT_BOARD_FROM_TOP, Some(BoardType::Left)).is_ok());
+        assert!(Board::try_new(MIDDLE_BOARD_FROM_RIGHT, Some(BoardType::Middle)).is_ok());
+        assert!(Board::try_new(RIGHT_BOARD_FROM_BOTTOM, Some(BoardType::Right)).is_ok());
+        assert!(Board::try_new(LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle)).is_ok());
+        assert!(Board::try_new(RIGHT_OR_MIDDLE_BOARD, Some(BoardType::RightOrMiddle)).is_ok());
+    }
+
+    #[test]
+    fn try_new_rejects_empty_board() {
+        let result = Board::try_new([[false; 5]; 4], None);
+        assert_eq!(result.err(), Some(InvalidPosition::NoTrack));
+    }
+
+    #[test]
+    fn try_new_rejects_disconnected_track() {
+        let (array, _) = parse_position(".###./...#./...#./...#.").unwrap();
+        assert!(Board::try_new(array, None).is_ok());
+        let (array, _) = parse_position(".###./...#./...../...#.").unwrap();
+        assert_eq!(Board::try_new(array, None).err(), Some(InvalidPosition::Disconnected(Coordinate { row: 3, col: 3 })));
+    }
+
+    #[test]
+    fn try_new_rejects_mismatched_arrangement() {
+        let result = Board::try_new(LEFT_OR_MIDDLE_BOARD, None);
+        assert_eq!(result.err(), Some(InvalidPosition::ArrangementMismatch(Coordinate { row: 1, col: 0 }, None)));
+        let result = Board::try_new(LEFT_BOARD_FROM_TOP, Some(BoardType::Right));
+        assert_eq!(result.err(), Some(InvalidPosition::ArrangementMismatch(Coordinate { row: 2, col: 0 }, Some(BoardType::Right))));
+        let result = Board::try_new(LEFT_BOARD_FROM_TOP, Some(BoardType::LeftOrMiddle));
+        assert_eq!(result.err(), Some(InvalidPosition::ArrangementMismatch(Coordinate { row: 2, col: 0 }, Some(BoardType::LeftOrMiddle))));
+    }
+
+    #[test]
+    fn try_new_rejects_unchosen_arrangement() {
+        for &bt in [BoardType::Left, BoardType::LeftOrMiddle, BoardType::Right].iter() {
+            assert_eq!(Board::try_new(START_BOARD, Some(bt)).err(), Some(InvalidPosition::ArrangementUnchosen(bt)));
+        }
+        assert!(Board::try_new(START_BOARD, None).is_ok());
+    }
+
+    #[test]
+    fn try_new_rejects_dominated_arrangement() {
+        // A lone (row 1, col 0) chooses LeftOrMiddle or Right, never Left or Middle themselves.
+        let (array, _) = parse_position("####./#..#./...#./.....").unwrap();
+        for &bt in [BoardType::Left, BoardType::Middle].iter() {
+            assert_eq!(Board::try_new(array, Some(bt)).err(), Some(InvalidPosition::ArrangementUnchosen(bt)));
+        }
+        assert!(Board::try_new(array, Some(BoardType::LeftOrMiddle)).is_ok());
+        assert!(Board::try_new(array, Some(BoardType::Right)).is_ok());
+        // Another square there lets LeftOrMiddle be narrowed to Left.
+        let (array, _) = parse_position("####./#..#./#..#./.....").unwrap();
+        assert!(Board::try_new(array, Some(BoardType::Left)).is_ok());
+    }
+
+    #[test]
+    fn try_new_rejects_finished_board() {
+        let result = Board::try_new(FINISHED_MIDDLE_BOARD, Some(BoardType::Middle));
+        assert_eq!(result.err(), Some(InvalidPosition::Finished));
+    }
+
+    #[test]
+    fn explains_incompatible_square() {
+        let board = Board::new(LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle));
+        let reason = board.check_move(mv(1, 1, MoveType::Single)).unwrap_err();
+        assert_eq!(reason.to_string(), "(row 1, col 1) requires Right but board is LeftOrMiddle");
+    }
+
+    #[test]
+    fn explains_incompatible_choice() {
+        let board = Board::new(START_BOARD, None);
+        let reason = board.check_move(mv(1, 1, MoveType::Single).with_board_type(BoardType::Middle)).unwrap_err();
+        assert_eq!(reason, IllegalMove::IncompatibleChoice(Coordinate { row: 1, col: 1 }, BoardType::Middle));
+        assert_eq!(reason.to_string(), "(row 1, col 1) requires Right but the move chooses Middle");
+    }
+
+    #[test]
+    fn legal_moves_place_distinct_squares() {
+        for &(array, board_type) in [(START_BOARD, None), (LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle)), (MIDDLE_BOARD_FROM_RIGHT, Some(BoardType::Middle))].iter() {
+            let moves = Board::new(array, board_type).legal_moves();
+            for (i, a) in moves.iter().enumerate() {
+                assert_eq!(*a, a.canonical());
+                for b in moves[i + 1..].iter() {
+                    assert!(*a.squares() != *b.squares() || a.new_board_type != b.new_board_type, "{} and {} are the same", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn legal_moves_include_every_placement() {
+        let board = Board::new(START_BOARD, None);
+        let moves = board.legal_moves();
+        assert!(moves.contains(&mv(3, 2, MoveType::OneRight)));
+        assert!(moves.contains(&mv(3, 3, MoveType::OneLeft).canonical()));
+        assert!(moves.contains(&mv(1, 2, MoveType::TwoDown)));
+        assert!(moves.contains(&mv(3, 3, MoveType::LeftAndRight).canonical()));
+    }
+
+    #[test]
+    fn legal_moves_iter_survives_moves_on_the_board() {
+        let mut board = Board::new(START_BOARD, None);
+        let expected = board.legal_moves();
+        let mut seen = Vec::new();
+        for m in board.legal_moves_iter() {
+            board.make_move(m);
+            board.undo_move();
+            seen.push(m);
+        }
+        assert_eq!(seen, expected);
+    }
+
+    // Plays every line a few moves deep, checking the frontier after each make and undo.
+    fn check_frontier_to_depth(board: &mut Board, depth: usize) {
+        assert!(board.frontier_is_current());
+        if depth == 0 {
+            return;
+        }
+        for m in board.legal_moves_iter() {
+            board.make_move(m);
+            check_frontier_to_depth(board, depth - 1);
+            board.undo_move();
+            assert!(board.frontier_is_current());
+        }
+    }
+
+    #[test]
+    fn frontier_follows_moves_and_undos() {
+        check_frontier_to_depth(&mut Board::new(START_BOARD, None), 3);
+        check_frontier_to_depth(&mut Board::new(LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle)), 3);
+    }
+
+    #[test]
+    fn position_apply_matches_make_move() {
+        let mut board = Board::new(START_BOARD, None);
+        let position = board.position(Player::One);
+        for m in board.legal_moves() {
+            board.make_move(m);
+            let after = position.apply(m);
+            assert_eq!(after, board.position(Player::Two));
+            assert_eq!(after.legal_moves(), board.legal_moves());
+            board.undo_move();
+        }
+    }
+
+    #[test]
+    fn positions_reached_by_different_orders_are_equal() {
+        let position = Board::new(START_BOARD, None).position(Player::One);
+        let a = mv(0, 4, MoveType::Single);
+        let b = mv(3, 2, MoveType::OneRight);
+        let c = mv(0, 0, MoveType::Single);
+        let one_way = position.apply(a).apply(b).apply(c);
+        let other_way = position.apply(c).apply(b).apply(a);
+        assert_eq!(one_way, other_way);
+        assert_eq!(one_way.to_move(), Player::Two);
+
+        let mut seen = HashSet::new();
+        seen.insert(one_way);
+        assert!(seen.contains(&other_way));
+        assert!(!seen.contains(&position.apply(a).apply(b)));
+    }
+
+    #[test]
+    fn only_the_left_arrangement_is_symmetric() {
+        assert!(Symmetry::FlipRows.preserves(Some(BoardType::Left)));
+        assert!(!Symmetry::FlipCols.preserves(Some(BoardType::Left)));
+        assert!(!Symmetry::Rotate.preserves(Some(BoardType::Left)));
+        for &bt in [None, Some(BoardType::LeftOrMiddle), Some(BoardType::Middle), Some(BoardType::RightOrMiddle), Some(BoardType::Right)].iter() {
+            assert!(!Symmetry::FlipRows.preserves(bt));
+        }
+    }
+
+    #[test]
+    fn mirrored_positions_share_a_canonical_form() {
+        let top: BoardArray = [
+            [ true,  true,  true,  true, false],
+            [ true, false, false,  true, false],
+            [ true, false, false, false, false],
+            [false, false, false, false, false],
+        ];
+        let bottom: BoardArray = [
+            [false, false, false, false, false],
+            [ true, false, false, false, false],
+            [ true, false, false,  true, false],
+            [ true,  true,  true,  true, false],
+        ];
+        let a = Position::new(top, Some(BoardType::Left), Player::One);
+        let b = Position::new(bottom, Some(BoardType::Left), Player::One);
+        assert_eq!(a.canonical(), b.canonical());
+        // The frontier is mirrored along with the track.
+        let canonical = a.canonical();
+        assert_eq!(canonical, Position::new(canonical.array(), canonical.board_type(), canonical.to_move()));
+        assert_eq!(a.apply(mv(3, 0, MoveType::Single)).canonical(), b.apply(mv(0, 0, MoveType::Single)).canonical());
+
+        // Not once the arrangement is Middle, whose railroad doesn't run through (row 3, col 0).
+        let a = Position::new(top, Some(BoardType::Middle), Player::One);
+        let b = Position::new(bottom, Some(BoardType::Middle), Player::One);
+        assert_ne!(a.canonical(), b.canonical());
+    }
+
+    #[test]
+    fn positions_round_trip_through_encoding() {
+        let mut board = Board::new(START_BOARD, None);
+        let mut positions = vec![board.position(Player::One), board.position(Player::Two)];
+        for m in board.legal_moves() {
+            board.make_move(m);
+            positions.push(board.position(Player::Two));
+            board.undo_move();
+        }
+        for position in positions {
+            assert_eq!(Position::decode(position.encode()), Some(position));
+        }
+        assert_eq!(Position::decode(6 << 20), None);
+        assert_eq!(Position::decode(1 << 24), None);
+    }
+
+    fn sample_boards() -> Vec<Board> {
+        vec![
+            Board::new(START_BOARD, None),
+            Board::new(LEFT_BOARD_FROM_BOTTOM, Some(BoardType::Left)),
+            Board::new(MIDDLE_BOARD_FROM_RIGHT, Some(BoardType::Middle)),
+            Board::new(LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle)),
+            Board::new(RIGHT_OR_MIDDLE_BOARD, Some(BoardType::RightOrMiddle)),
+            Board::new(FINISHED_RIGHT_BOARD, Some(BoardType::Right)),
+        ]
+    }
+
+    #[test]
+    fn predecessors_include_every_legal_move() {
+        for mut board in sample_boards() {
+            let (array, board_type) = (board.array(), board.board_type());
+            for m in board.legal_moves() {
+                board.make_move(m);
+                let found = board.predecessors().iter().any(|&(ref earlier, mov)| {
+                    mov == m && earlier.array() == array && earlier.board_type() == board_type
+                });
+                assert!(found, "{} from {} is missing", m, format_position(&array, board_type));
+                board.undo_move();
+            }
+        }
+    }
+
+    #[test]
+    fn predecessors_lead_here() {
+        for board in sample_boards() {
+            for (mut earlier, m) in board.predecessors() {
+                assert!(earlier.legal_moves().contains(&m));
+                earlier.make_move(m);
+                assert_eq!(earlier.array(), board.array());
+                assert_eq!(earlier.board_type(), board.board_type());
+            }
+        }
+    }
+
+    #[test]
+    fn predecessors_widen_the_board_type() {
+        let board = Board::new(LEFT_OR_MIDDLE_BOARD, Some(BoardType::LeftOrMiddle));
+        let predecessors = board.predecessors();
+        // Placing (row 1, col 0) chose LeftOrMiddle; before it, no type was chosen.
+        assert!(predecessors.iter().any(|&(ref earlier, m)| {
+            earlier.board_type().is_none() && m == mv(1, 0, MoveType::Single).with_board_type(BoardType::LeftOrMiddle)
+        }));
+        // Placing (row 2, col 2) didn't choose one.
+        assert!(predecessors.iter().any(|&(ref earlier, m)| {
+            earlier.board_type() == Some(BoardType::LeftOrMiddle) && m == mv(2, 2, MoveType::Single)
+        }));
+        // Nothing comes before a single square of track.
+        let mut lone = [[false; NUM_COLS]; NUM_ROWS];
+        lone[0][2] = true;
+        assert!(Board::new(lone, None).predecessors().is_empty());
+    }
 }
@@ -0,0 +1,138 @@
+// Solve caches remember the winner of every position solved in earlier runs, for example:
+//
+//     rules 1
+//     ####./#..#./#..#./.....:Left 1 2
+//
+// Each line after the first holds a position, the player to move, and the winner.
+// Positions are written in canonical form, as the solver remembers them.
+//
+// Entries are checked when read, so that a position that couldn't arise in a game,
+// say from a cache edited by hand, isn't remembered; their winners are trusted unless verified.
+//
+// The rules version changes whenever a change to the rules or to the solver could
+// change a winner, so that caches written before the change are not trusted.
+
+use board::{self, Board, InvalidPosition, Position};
+use player::Player;
+use solver::Solver;
+
+pub const RULES_VERSION: u32 = 1;
+
+fn player_number(player: Player) -> u32 {
+    match player {
+        Player::One => 1,
+        Player::Two => 2,
+    }
+}
+
+fn parse_player(s: &str) -> Result<Player, String> {
+    match s {
+        "1" => Ok(Player::One),
+        "2" => Ok(Player::Two),
+        _ => Err(format!("Unknown player {}", s)),
+    }
+}
+
+pub fn write(solver: &Solver) -> String {
+    let mut s = format!("rules {}\n", RULES_VERSION);
+    for (position, winner) in solver.solved() {
+        s.push_str(&format!(
+            "{} {} {}\n",
+            board::format_position(&position.array(), position.board_type()),
+            player_number(position.to_move()),
+            player_number(winner),
+        ));
+    }
+    s
+}
+
+// Reads the positions and winners of a cache, which must be for the current rules.
+pub fn read(s: &str) -> Result<Vec<(Position, Player)>, String> {
+    let mut lines = s.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+    let version = match lines.next() {
+        Some(line) if line.starts_with("rules ") => line["rules ".len()..].trim(),
+        _ => return Err("Missing rules version".to_string()),
+    };
+    if version != RULES_VERSION.to_string() {
+        return Err(format!("Written for rules version {}, but these are version {}", version, RULES_VERSION));
+    }
+
+    let mut entries = Vec::new();
+    for line in lines {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(format!("Expected POSITION PLAYER WINNER, got {}", line));
+        }
+        let (array, board_type) = board::parse_position(fields[0])?;
+        // The solver remembers finished positions too: they are lost for the player to move.
+        match Board::try_new(array, board_type) {
+            Ok(_) | Err(InvalidPosition::Finished) => {},
+            Err(e) => return Err(format!("Invalid position {}: {}", fields[0], e)),
+        }
+        let position = Position::new(array, board_type, parse_player(fields[1])?);
+        entries.push((position, parse_player(fields[2])?));
+    }
+    Ok(entries)
+}
+
+// Solves each position again without the cache, returning the entries whose winner is wrong.
+pub fn verify(entries: &[(Position, Player)]) -> Vec<(Position, Player)> {
+    let mut solver = Solver::new();
+    entries.iter().cloned().filter(|&(position, winner)| {
+        solver.game_result(position).0.winner != winner
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, verify, write};
+    use board::{self, Board};
+    use player::Player;
+    use solver::Solver;
+
+    const SMALL_GAME: &str = "####./#..#./#..#./.....:Left";
+
+    #[test]
+    fn round_trips_solved_positions() {
+        let (array, board_type) = board::parse_position(SMALL_GAME).unwrap();
+        let mut solver = Solver::new();
+        solver.game_result(Board::new(array, board_type).position(Player::One));
+        assert!(solver.positions_solved() > 0);
+
+        let entries = read(&write(&solver)).unwrap();
+        assert_eq!(entries, solver.solved());
+        assert!(verify(&entries).is_empty());
+
+        let mut loaded = Solver::new();
+        for &(position, winner) in entries.iter() {
+            loaded.remember(position, winner);
+        }
+        assert_eq!(loaded.solved(), solver.solved());
+    }
+
+    #[test]
+    fn verify_finds_wrong_winners() {
+        let (array, board_type) = board::parse_position(SMALL_GAME).unwrap();
+        let mut solver = Solver::new();
+        solver.game_result(Board::new(array, board_type).position(Player::One));
+        let mut entries = solver.solved();
+        entries[0].1 = entries[0].1.opponent();
+        assert_eq!(verify(&entries), vec![entries[0]]);
+    }
+
+    #[test]
+    fn rejects_other_rules_versions() {
+        assert!(read("rules 0\n").is_err());
+        assert!(read("").is_err());
+        assert!(read("rules 1\n.###./...#./...#./..... 1\n").is_err());
+        assert_eq!(read("rules 1\n"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_impossible_positions() {
+        // Track cut off from the rest, and a board type that no track chose.
+        assert!(read("rules 1\n#...#/...../...../..... 1 2\n").is_err());
+        assert!(read("rules 1\n.###./...#./...#./.....:Left 1 2\n").is_err());
+        assert!(read("rules 1\n.###./...#./...#./..... 1 2\n").is_ok());
+    }
+}
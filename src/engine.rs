@@ -0,0 +1,257 @@
+// A computer player. It plays perfectly when it can solve the position within its limits,
+// and otherwise plays the best guess of a heuristic search.
+//
+// A fallible engine instead makes mistakes now and then, as a human might,
+// so that it can be used for practice, or to see how often a lost position can be saved.
+
+use action::Move;
+use board::Position;
+use heuristic::{AlphaBeta, Weights};
+use random::Rng;
+use solver::{self, Deadline, Limits, Outcome, Search, Solver};
+use std::time::Duration;
+
+// How well a fallible engine plays.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Strength {
+    // The chance, from 0 to 1, of playing a move worse than the best when there is one.
+    pub error_rate: f64,
+    // How many moves ahead it looks, judging the positions there with the heuristic.
+    // If None, it knows the result of every move, and how likely a losing move is to be saved
+    // against a random reply, so its only mistakes are deliberate ones.
+    pub depth: Option<usize>,
+}
+
+pub struct Engine {
+    solver: Solver,
+    heuristic: AlphaBeta,
+    limits: Limits,
+    fallible: Option<(Strength, Rng)>,
+}
+
+impl Engine {
+    // An engine whose solver gives up at `limits`, after which the heuristic search
+    // has up to `heuristic_time` (and `limits` again) to guess.
+    pub fn new(weights: Weights, limits: Limits, heuristic_time: Duration) -> Engine {
+        Engine { solver: Solver::new(), heuristic: AlphaBeta::new(weights, heuristic_time), limits, fallible: None }
+    }
+
+    // An engine playing at `strength`, whose choices are the same for the same seed.
+    // Without a depth, it solves each position only until `limits`, and scores the moves
+    // with the heuristic instead if that isn't enough to know them all.
+    pub fn fallible(weights: Weights, strength: Strength, limits: Limits, seed: u64) -> Engine {
+        Engine {
+            solver: Solver::new(),
+            heuristic: AlphaBeta::new(weights, Duration::from_secs(0)),
+            limits,
+            fallible: Some((strength, Rng::new(seed))),
+        }
+    }
+
+    // The move to play, or None if the player to move has no moves.
+    pub fn choose(&mut self, position: Position) -> Option<Move> {
+        if let Some((strength, _)) = self.fallible {
+            return self.choose_fallibly(position, strength);
+        }
+        self.solver.set_deadline(self.limits.deadline(self.solver.nodes()));
+        if let Outcome::Proven(_, Some(winning_move)) = self.solver.search(position) {
+            return Some(winning_move);
+        }
+        // Either the position is lost, so every move is as good as another against a perfect opponent,
+        // or it couldn't be solved in time.
+        self.heuristic.set_deadline(self.limits.deadline(self.heuristic.nodes()));
+        match self.heuristic.search(position) {
+            Outcome::Proven(_, Some(m)) | Outcome::Unknown(Some(m)) => Some(m),
+            Outcome::Proven(_, None) | Outcome::Unknown(None) => position.legal_moves_iter().next(),
+        }
+    }
+
+    // Usually one of the best moves, as far as the engine can tell; otherwise a worse one.
+    fn choose_fallibly(&mut self, position: Position, strength: Strength) -> Option<Move> {
+        let mut scored = self.scores(position, strength.depth);
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).expect("scores are numbers"));
+        // Moves with the same score are equally good, so the moves are grouped by score, best first.
+        let mut groups: Vec<(f64, Vec<Move>)> = Vec::new();
+        for (i, &(score, m)) in scored.iter().enumerate() {
+            if i > 0 && scored[i - 1].0 == score {
+                groups.last_mut().expect("a group was started").1.push(m);
+            } else {
+                groups.push((score, vec![m]));
+            }
+        }
+        if groups.is_empty() {
+            return None;
+        }
+
+        let scores: Vec<f64> = groups.iter().map(|&(score, _)| score).collect();
+        let rng = &mut self.fallible.as_mut().expect("the engine is fallible").1;
+        let moves = &groups[choose_group(&scores, strength.error_rate, rng)].1;
+        Some(moves[rng.below(moves.len())])
+    }
+
+    // Every legal move, with how good making it looks for the player making it.
+    // Without a depth, that is its practical chances: 1 for a winning move, and otherwise the fraction
+    // of the opponent's replies that let the player win after all. With a depth, it is a heuristic score
+    // that many moves ahead, as it is one move ahead if the solver can't find every move's chances within the limits.
+    fn scores(&mut self, position: Position, depth: Option<usize>) -> Vec<(f64, Move)> {
+        if depth.is_none() {
+            self.solver.set_deadline(self.limits.deadline(self.solver.nodes()));
+            let (chances, unranked) = solver::practical_chances(&mut self.solver, position);
+            if unranked.is_empty() {
+                return chances.iter().map(|c| (c.fraction(), c.mv)).collect();
+            }
+        }
+        // Looking one move ahead takes a position per move, so it always finishes. Looking further,
+        // one move deeper at a time, stops at the limits, keeping the deepest scores that finished.
+        let mut scored = self.heuristic_scores(position, 1, Deadline::default()).expect("searches without a deadline finish");
+        let deadline = self.limits.deadline(self.heuristic.nodes());
+        for depth in 2..depth.unwrap_or(1) + 1 {
+            match self.heuristic_scores(position, depth, deadline) {
+                Some(deeper) => scored = deeper,
+                None => break,
+            }
+        }
+        scored
+    }
+
+    // Every legal move with its heuristic score `depth` moves ahead, counting the move itself,
+    // or None if `deadline` passes first.
+    fn heuristic_scores(&mut self, position: Position, depth: usize, deadline: Deadline) -> Option<Vec<(f64, Move)>> {
+        position.legal_moves().into_iter().map(|m| {
+            Some((-self.heuristic.score(position.apply(m), depth - 1, deadline)? as f64, m))
+        }).collect()
+    }
+}
+
+// The group of moves to play, given the groups' scores, best first: with chance `error_rate`, one of the worse ones.
+// The further a worse score falls short of the best, the less likely it is, down to the worst,
+// which is half as likely as a score just short of the best.
+fn choose_group(scores: &[f64], error_rate: f64, rng: &mut Rng) -> usize {
+    if scores.len() < 2 || rng.fraction() >= error_rate {
+        return 0;
+    }
+    let (best, worst) = (scores[0], scores[scores.len() - 1]);
+    let weights: Vec<f64> = scores[1..].iter().map(|score| 1.0 - 0.5 * (best - score) / (best - worst)).collect();
+    let mut x = rng.fraction() * weights.iter().sum::<f64>();
+    for (i, weight) in weights.iter().enumerate() {
+        if x < *weight {
+            return i + 1;
+        }
+        x -= weight;
+    }
+    scores.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{choose_group, Engine, Strength};
+    use board::{self, Board, Position};
+    use heuristic::{AlphaBeta, Weights};
+    use player::Player;
+    use random::Rng;
+    use solver::{Deadline, Limits, Search, Solver};
+    use std::time::{Duration, Instant};
+
+    fn position(s: &str, to_move: Player) -> Position {
+        let (array, board_type) = board::parse_position(s).unwrap();
+        Board::new(array, board_type).position(to_move)
+    }
+
+    #[test]
+    fn plays_perfectly_when_it_can_solve() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut engine = Engine::new(Weights::default(), Limits::default(), Duration::from_secs(1));
+        let m = engine.choose(start).unwrap();
+        let (result, _) = Solver::new().game_result(start.apply(m));
+        assert!(result.win_for(Player::One));
+    }
+
+    #[test]
+    fn guesses_when_it_cannot() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let limits = Limits { time: None, nodes: Some(10) };
+        let mut engine = Engine::new(Weights::default(), limits, Duration::from_secs(1));
+        let m = engine.choose(start).unwrap();
+        assert!(start.legal_moves().contains(&m));
+    }
+
+    #[test]
+    fn no_move_when_the_game_is_over() {
+        let finished = position("#####/#.###/#.###/#####:Left", Player::Two);
+        let mut engine = Engine::new(Weights::default(), Limits::default(), Duration::from_secs(1));
+        assert_eq!(engine.choose(finished), None);
+    }
+
+    #[test]
+    fn without_errors_it_only_plays_winning_moves() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut engine = Engine::fallible(Weights::default(), Strength { error_rate: 0.0, depth: None }, Limits::default(), 1);
+        let mut solver = Solver::new();
+        for _ in 0..20 {
+            let m = engine.choose(start).unwrap();
+            assert!(solver.game_result(start.apply(m)).0.win_for(Player::One));
+        }
+    }
+
+    #[test]
+    fn always_erring_it_only_plays_losing_moves() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut engine = Engine::fallible(Weights::default(), Strength { error_rate: 1.0, depth: None }, Limits::default(), 1);
+        let mut solver = Solver::new();
+        for _ in 0..20 {
+            let m = engine.choose(start).unwrap();
+            assert!(!solver.game_result(start.apply(m)).0.win_for(Player::One));
+        }
+    }
+
+    #[test]
+    fn same_seed_same_mistakes() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let strength = Strength { error_rate: 0.5, depth: Some(2) };
+        let mut a = Engine::fallible(Weights::default(), strength, Limits::default(), 5);
+        let mut b = Engine::fallible(Weights::default(), strength, Limits::default(), 5);
+        let xs: Vec<_> = (0..10).map(|_| a.choose(start).unwrap()).collect();
+        let ys: Vec<_> = (0..10).map(|_| b.choose(start).unwrap()).collect();
+        assert_eq!(xs, ys);
+        assert!(xs.iter().all(|m| start.legal_moves().contains(m)));
+    }
+
+    #[test]
+    fn worse_mistakes_are_less_likely() {
+        let mut rng = Rng::new(3);
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[choose_group(&[1.0, 0.9, 0.0], 1.0, &mut rng)] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!(counts[1] > counts[2] && counts[2] > 0);
+        assert_eq!(choose_group(&[1.0, 0.9, 0.0], 0.0, &mut rng), 0);
+        assert_eq!(choose_group(&[1.0], 1.0, &mut rng), 0);
+    }
+
+    #[test]
+    fn deep_heuristic_scores_stop_at_the_limits() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let limits = Limits { time: None, nodes: Some(100) };
+        let mut engine = Engine::fallible(Weights::default(), Strength { error_rate: 0.5, depth: Some(14) }, limits, 1);
+        let began = Instant::now();
+        for _ in 0..10 {
+            assert!(start.legal_moves().contains(&engine.choose(start).unwrap()));
+        }
+        assert!(began.elapsed() < Duration::from_secs(1));
+        let moves = start.legal_moves().len() as u64;
+        assert!(engine.heuristic.nodes() <= 10 * (moves + 100));
+    }
+
+    #[test]
+    fn out_of_limits_it_falls_back_on_the_heuristic() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let limits = Limits { time: None, nodes: Some(10) };
+        let mut engine = Engine::fallible(Weights::default(), Strength { error_rate: 0.0, depth: None }, limits, 1);
+        let mut heuristic = AlphaBeta::new(Weights::default(), Duration::from_secs(0));
+        let mut score = |m| -heuristic.score(start.apply(m), 0, Deadline::default()).unwrap();
+        let best = start.legal_moves().into_iter().map(&mut score).max();
+        let m = engine.choose(start).unwrap();
+        assert_eq!(Some(score(m)), best);
+    }
+}
@@ -0,0 +1,374 @@
+use action::Move;
+use board::{Board, IllegalMove};
+use player::Player;
+
+// A position in the variation tree.
+// The root has no move; the first child of each node continues its main line.
+struct Node {
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+// A move together with all the variations that have been explored after it.
+pub struct Variation {
+    pub mv: Move,
+    pub continuations: Vec<Variation>,
+}
+
+// A game in progress: the board, whose turn it is,
+// and the tree of variations that have been explored.
+pub struct Game {
+    board: Board,
+    start: Board,
+    first_player: Player,
+    // Deleted variations are unlinked from their parent, and their nodes are reused for later moves.
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    current: usize,
+    winner: Option<Player>,
+}
+
+impl Game {
+    // A game starting from the board's position, with `first_player` to move.
+    // Any moves already made on the board are forgotten, so that they can't be undone past the start.
+    pub fn new(board: Board, first_player: Player) -> Game {
+        let board = Board::new(board.array(), board.board_type());
+        let mut game = Game {
+            start: board.clone(),
+            board,
+            first_player,
+            nodes: vec![Node { mv: None, parent: None, children: Vec::new() }],
+            free: Vec::new(),
+            current: 0,
+            winner: None,
+        };
+        game.update_outcome();
+        game
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    // The board before any moves of this game were made.
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    pub fn first_player(&self) -> Player {
+        self.first_player
+    }
+
+    // The player to move.
+    pub fn player(&self) -> Player {
+        if self.board.num_moves().is_multiple_of(2) {
+            self.first_player
+        } else {
+            self.first_player.opponent()
+        }
+    }
+
+    // The current turn, starting from 1.
+    pub fn turn(&self) -> usize {
+        self.board.num_moves() + 1
+    }
+
+    // The number of moves made so far.
+    pub fn ply(&self) -> usize {
+        self.board.num_moves()
+    }
+
+    // The moves made so far, followed by the main line from the current position.
+    pub fn history(&self) -> Vec<Move> {
+        let mut moves = self.board.moves_made();
+        let mut node = self.current;
+        while let Some(&child) = self.nodes[node].children.first() {
+            moves.extend(self.nodes[child].mv);
+            node = child;
+        }
+        moves
+    }
+
+    // The moves that have been explored from the current position, main line first.
+    pub fn variations(&self) -> Vec<Move> {
+        self.nodes[self.current].children.iter().filter_map(|&child| self.nodes[child].mv).collect()
+    }
+
+    // Every variation explored from the start of the game.
+    pub fn variation_tree(&self) -> Vec<Variation> {
+        self.subtree(0)
+    }
+
+    fn subtree(&self, node: usize) -> Vec<Variation> {
+        self.nodes[node].children.iter().map(|&child| Variation {
+            mv: self.nodes[child].mv.expect("only the root has no move"),
+            continuations: self.subtree(child),
+        }).collect()
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.board.legal_moves()
+    }
+
+    // The winner, if the game is over.
+    pub fn outcome(&self) -> Option<Player> {
+        self.winner
+    }
+
+    // Plays a move, if it is legal, recording it in canonical form.
+    // If the move has already been explored, this follows that variation;
+    // otherwise the move starts a new one.
+    pub fn play(&mut self, m: Move) -> Result<(), IllegalMove> {
+        // Checked before canonicalizing, which only makes sense for moves on the board.
+        self.board.check_move(m)?;
+        let m = m.canonical();
+        self.board.make_move(m);
+        let existing = self.nodes[self.current].children.iter().cloned().find(|&child| {
+            self.nodes[child].mv == Some(m)
+        });
+        self.current = match existing {
+            Some(child) => child,
+            None => {
+                let node = Node { mv: Some(m), parent: Some(self.current), children: Vec::new() };
+                let child = match self.free.pop() {
+                    Some(child) => {
+                        self.nodes[child] = node;
+                        child
+                    },
+                    None => {
+                        self.nodes.push(node);
+                        self.nodes.len() - 1
+                    },
+                };
+                self.nodes[self.current].children.push(child);
+                child
+            },
+        };
+        self.update_outcome();
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Option<Move> {
+        let parent = self.nodes[self.current].parent?;
+        let undone = self.board.undo_move();
+        self.current = parent;
+        self.update_outcome();
+        undone
+    }
+
+    // Follows the main line from the current position by one move.
+    pub fn redo(&mut self) -> Option<Move> {
+        let child = *self.nodes[self.current].children.first()?;
+        let m = self.nodes[child].mv.expect("only the root has no move");
+        self.board.make_move(m);
+        self.current = child;
+        self.update_outcome();
+        Some(m)
+    }
+
+    // Undoes moves, or redoes them along the main line, until `ply` moves have been made.
+    // Returns false, without moving, if `ply` is past the end of the history.
+    pub fn goto(&mut self, ply: usize) -> bool {
+        if ply > self.history().len() {
+            return false;
+        }
+        while self.ply() > ply {
+            self.undo();
+        }
+        while self.ply() < ply {
+            self.redo();
+        }
+        true
+    }
+
+    // Makes the `i`th variation from the current position the main line.
+    pub fn promote_variation(&mut self, i: usize) -> bool {
+        let children = &mut self.nodes[self.current].children;
+        if i >= children.len() {
+            return false;
+        }
+        let child = children.remove(i);
+        children.insert(0, child);
+        true
+    }
+
+    // Deletes the `i`th variation from the current position and everything after it.
+    pub fn delete_variation(&mut self, i: usize) -> Option<Move> {
+        let children = &mut self.nodes[self.current].children;
+        if i >= children.len() {
+            return None;
+        }
+        let child = children.remove(i);
+        let mv = self.nodes[child].mv;
+        let mut to_free = vec![child];
+        while let Some(node) = to_free.pop() {
+            to_free.append(&mut self.nodes[node].children);
+            self.free.push(node);
+        }
+        mv
+    }
+
+    fn update_outcome(&mut self) {
+        // Whoever has no moves left lost, since their opponent completed the railroad.
+        self.winner = if self.board.legal_moves_iter().next().is_none() {
+            Some(self.player().opponent())
+        } else {
+            None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Game;
+    use board::{Board,BoardArray,BoardType,IllegalMove};
+    use player::Player;
+
+    const START_BOARD: BoardArray = [
+        [false,  true,  true,  true, false],
+        [false, false, false,  true, false],
+        [false, false, false,  true, false],
+        [false, false, false, false, false],
+    ];
+
+    const NEARLY_FINISHED_BOARD: BoardArray = [
+        [ true,  true,  true,  true,  true],
+        [ true, false,  true,  true,  true],
+        [ true, false,  true,  true,  true],
+        [false,  true,  true,  true,  true],
+    ];
+
+    #[test]
+    fn play_passes_turn() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::Two);
+        assert_eq!(game.player(), Player::Two);
+        let m = game.legal_moves()[0];
+        game.play(m).unwrap();
+        assert_eq!(game.player(), Player::One);
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn moves_off_the_board_are_illegal() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        for notation in ["OneLeft@3,0", "TwoUp@1,4", "Single@4,0", "OneRight@0,9"].iter() {
+            assert_eq!(game.play(notation.parse().unwrap()), Err(IllegalMove::OutOfBounds));
+        }
+        assert_eq!(game.ply(), 0);
+        assert!(game.variations().is_empty());
+    }
+
+    #[test]
+    fn starts_from_the_board_position_forgetting_its_moves() {
+        let mut board = Board::new(START_BOARD, None);
+        let m = board.legal_moves()[0];
+        board.make_move(m);
+        let game = Game::new(board.clone(), Player::Two);
+        assert_eq!(game.player(), Player::Two);
+        assert_eq!(game.ply(), 0);
+        assert!(game.history().is_empty());
+        assert_eq!(game.start().array(), board.array());
+        assert_eq!(game.board().num_moves(), 0);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        let m = game.legal_moves()[0];
+        game.play(m).unwrap();
+        assert_eq!(game.undo(), Some(m));
+        assert_eq!(game.player(), Player::One);
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.redo(), Some(m));
+        assert_eq!(game.player(), Player::Two);
+        assert_eq!(game.redo(), None);
+    }
+
+    #[test]
+    fn playing_other_move_adds_variation() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        let moves = game.legal_moves();
+        game.play(moves[0]).unwrap();
+        game.undo();
+        game.play(moves[1]).unwrap();
+        game.undo();
+        assert_eq!(game.variations(), vec![moves[0], moves[1]]);
+        assert_eq!(game.redo(), Some(moves[0]));
+    }
+
+    #[test]
+    fn replaying_explored_move_follows_variation() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        let moves = game.legal_moves();
+        game.play(moves[0]).unwrap();
+        game.undo();
+        game.play(moves[1]).unwrap();
+        let reply = game.legal_moves()[0];
+        game.play(reply).unwrap();
+        game.goto(0);
+        game.play(moves[1]).unwrap();
+        assert_eq!(game.history(), vec![moves[1], reply]);
+    }
+
+    #[test]
+    fn promote_and_delete_variations() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        let moves = game.legal_moves();
+        for m in moves.iter().take(3) {
+            game.play(*m).unwrap();
+            game.undo();
+        }
+        assert!(game.promote_variation(2));
+        assert_eq!(game.variations(), vec![moves[2], moves[0], moves[1]]);
+        assert!(!game.promote_variation(3));
+        assert_eq!(game.delete_variation(1), Some(moves[0]));
+        assert_eq!(game.variations(), vec![moves[2], moves[1]]);
+        assert_eq!(game.history(), vec![moves[2]]);
+        assert_eq!(game.delete_variation(2), None);
+    }
+
+    #[test]
+    fn deleted_variations_make_room_for_new_ones() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        let moves = game.legal_moves();
+        for _ in 0..10 {
+            game.play(moves[0]).unwrap();
+            let reply = game.legal_moves()[0];
+            game.play(reply).unwrap();
+            game.goto(0);
+            assert_eq!(game.delete_variation(0), Some(moves[0]));
+        }
+        assert_eq!(game.nodes.len(), 3);
+        assert!(game.variations().is_empty());
+    }
+
+    #[test]
+    fn goto_moves_within_history() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::One);
+        let first = game.legal_moves()[0];
+        game.play(first).unwrap();
+        let second = game.legal_moves()[0];
+        game.play(second).unwrap();
+        assert!(game.goto(0));
+        assert_eq!(game.ply(), 0);
+        assert_eq!(game.history(), vec![first, second]);
+        assert!(game.goto(2));
+        assert_eq!(game.board().moves_made(), vec![first, second]);
+        assert!(!game.goto(3));
+        assert_eq!(game.ply(), 2);
+    }
+
+    #[test]
+    fn finishing_the_railroad_wins() {
+        let mut game = Game::new(Board::new(NEARLY_FINISHED_BOARD, Some(BoardType::Left)), Player::One);
+        assert_eq!(game.outcome(), None);
+        let moves = game.legal_moves();
+        assert_eq!(moves.len(), 1);
+        game.play(moves[0]).unwrap();
+        assert_eq!(game.outcome(), Some(Player::One));
+        assert_eq!(game.play(moves[0]), Err(IllegalMove::GameOver));
+        game.undo();
+        assert_eq!(game.outcome(), None);
+    }
+}
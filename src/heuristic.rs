@@ -0,0 +1,206 @@
+// A guess at who is winning a position, for searches that can't see to the end of the game,
+// and a depth-limited alpha-beta search that uses it.
+//
+// The search deepens one move at a time until it runs out of time, so it always has
+// the best move of the deepest search it finished. Once it is deep enough to see the end
+// of every line, its answer is exact.
+
+use action::Move;
+use board::Position;
+use solver::{Deadline, GameResult, Limits, Outcome, Search};
+use std::time::Duration;
+
+// Far more than any evaluation, so that finished games always outweigh guesses.
+const WIN: i32 = 1_000_000;
+
+// How much each feature of a position counts, for the player to move.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Weights {
+    // Counted as 1 when an odd number of squares is left, and -1 when an even number is:
+    // if every move placed one square, the player to move would place the last one.
+    pub parity: i32,
+    // Each empty square next to track, where either player could play next.
+    pub frontier: i32,
+    // Each empty square left on the railroad.
+    pub squares_left: i32,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights { parity: 4, frontier: -1, squares_left: 1 }
+    }
+}
+
+// How good the position looks for the player to move.
+// Positions without moves are not evaluated; they are lost.
+pub fn evaluate(position: &Position, weights: &Weights) -> i32 {
+    let squares_left = position.squares_left() as i32;
+    let parity = if squares_left % 2 == 1 { 1 } else { -1 };
+    weights.parity * parity + weights.frontier * position.frontier_size() as i32 + weights.squares_left * squares_left
+}
+
+pub struct AlphaBeta {
+    weights: Weights,
+    time_limit: Duration,
+    nodes: u64,
+    // The deepest search finished by the last call to `search`.
+    depth: usize,
+    // Set from outside, and the sooner of that and the time limit during a search.
+    deadline: Deadline,
+    stop: Deadline,
+}
+
+impl AlphaBeta {
+    pub fn new(weights: Weights, time_limit: Duration) -> AlphaBeta {
+        AlphaBeta { weights, time_limit, nodes: 0, depth: 0, deadline: Deadline::default(), stop: Deadline::default() }
+    }
+
+    // The number of moves ahead the last search looked.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // How good `position` looks for the player to move, searching `depth` moves ahead,
+    // or None if `deadline` passes first. The time limit and the deadline of `search` don't apply.
+    pub fn score(&mut self, position: Position, depth: usize, deadline: Deadline) -> Option<i32> {
+        self.stop = deadline;
+        self.negamax(position, depth, -WIN, WIN)
+    }
+
+    // The score of the position and its best move, searching `depth` moves ahead.
+    // None if time ran out first.
+    fn root(&mut self, position: Position, depth: usize, first: Option<Move>) -> Option<(i32, Option<Move>)> {
+        let mut moves = position.legal_moves();
+        // The best move of the last, shallower search is likely still good, and trying it
+        // first lets the others be cut off sooner.
+        if let Some(i) = first.and_then(|m| moves.iter().position(|&x| x == m)) {
+            moves.swap(0, i);
+        }
+        let mut best = (-WIN, None);
+        let mut alpha = -WIN;
+        for m in moves {
+            let score = -self.negamax(position.apply(m), depth - 1, -WIN, -alpha)?;
+            if best.1.is_none() || score > best.0 {
+                best = (score, Some(m));
+            }
+            alpha = alpha.max(score);
+        }
+        Some(best)
+    }
+
+    fn negamax(&mut self, position: Position, depth: usize, mut alpha: i32, beta: i32) -> Option<i32> {
+        self.nodes += 1;
+        if self.stop.passed(self.nodes) {
+            return None;
+        }
+
+        let mut moves = position.legal_moves_iter().peekable();
+        if moves.peek().is_none() {
+            return Some(-WIN);
+        }
+        if depth == 0 {
+            return Some(evaluate(&position, &self.weights));
+        }
+        let mut best = -WIN;
+        for m in moves {
+            let score = -self.negamax(position.apply(m), depth - 1, -beta, -alpha)?;
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Some(best)
+    }
+}
+
+impl Search for AlphaBeta {
+    fn search(&mut self, position: Position) -> Outcome {
+        let player = position.to_move();
+        self.stop = self.deadline.sooner(Limits { time: Some(self.time_limit), nodes: None }.deadline(self.nodes));
+        self.depth = 0;
+        self.nodes += 1;
+        let mut guess = None;
+        // A game can't last longer than there are squares left, so by then the search
+        // sees every line to the end and its score is a win or a loss.
+        for depth in 1..position.squares_left() + 1 {
+            let (score, best) = match self.root(position, depth, guess) {
+                Some(result) => result,
+                None => break,
+            };
+            self.depth = depth;
+            guess = best;
+            if score >= WIN {
+                return Outcome::Proven(GameResult { winner: player }, best);
+            } else if score <= -WIN {
+                return Outcome::Proven(GameResult { winner: player.opponent() }, None);
+            }
+        }
+        if position.legal_moves_iter().next().is_none() {
+            return Outcome::Proven(GameResult { winner: player.opponent() }, None);
+        }
+        Outcome::Unknown(guess)
+    }
+
+    fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    fn nodes(&self) -> u64 {
+        self.nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, AlphaBeta, Weights};
+    use board::{self, Board, Position};
+    use player::Player;
+    use solver::{Outcome, Search, Solver};
+    use std::time::Duration;
+
+    fn position(s: &str, to_move: Player) -> Position {
+        let (array, board_type) = board::parse_position(s).unwrap();
+        Board::new(array, board_type).position(to_move)
+    }
+
+    #[test]
+    fn weights_pick_out_features() {
+        // 18 squares on the railroad, 5 with track. Next to the track are (row 0, col 0),
+        // (row 0, col 4), (row 1, col 1), (row 1, col 2), (row 1, col 4), (row 2, col 2),
+        // (row 2, col 4) and (row 3, col 3).
+        let start = position(".###./...#./...#./.....", Player::One);
+        assert_eq!(start.squares_left(), 13);
+        assert_eq!(start.frontier_size(), 8);
+        assert_eq!(evaluate(&start, &Weights { parity: 1, frontier: 0, squares_left: 0 }), 1);
+        assert_eq!(evaluate(&start, &Weights { parity: 0, frontier: 1, squares_left: 0 }), 8);
+        assert_eq!(evaluate(&start, &Weights { parity: 0, frontier: 0, squares_left: 1 }), 13);
+        assert_eq!(evaluate(&start, &Weights { parity: 2, frontier: 3, squares_left: -1 }), 2 + 24 - 13);
+    }
+
+    #[test]
+    fn deep_enough_searches_are_exact() {
+        let nearly_done = position("####./#..#./#..#./.....:Left", Player::Two);
+        let (result, _) = Solver::new().game_result(nearly_done);
+        let mut search = AlphaBeta::new(Weights::default(), Duration::from_secs(60));
+        match search.search(nearly_done) {
+            Outcome::Proven(proven, winning_move) => {
+                assert_eq!(proven, result);
+                if let Some(m) = winning_move {
+                    assert_eq!(Solver::new().game_result(nearly_done.apply(m)).0, result);
+                }
+            },
+            outcome => panic!("expected a proof, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn out_of_time_gives_a_guess() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut search = AlphaBeta::new(Weights::default(), Duration::from_secs(0));
+        match search.search(start) {
+            Outcome::Unknown(Some(m)) => assert!(start.legal_moves().contains(&m)),
+            outcome => panic!("expected a guess, got {:?}", outcome),
+        }
+    }
+}
@@ -1,3 +1,15 @@
 pub mod action;
+pub mod agent;
 pub mod board;
+pub mod cache;
+pub mod engine;
+pub mod game;
+pub mod heuristic;
+pub mod mcts;
 pub mod player;
+pub mod proof_number;
+pub mod random;
+pub mod reachability;
+pub mod record;
+pub mod solver;
+pub mod tablebase;
@@ -1,100 +1,321 @@
+extern crate getopts;
 extern crate monorail;
 extern crate term;
 
+use getopts::Options;
 use monorail::action::Move;
-use monorail::board::Board;
-use monorail::player::Player;
+use monorail::agent::{self, Agent, HeuristicAgent, HumanAgent, MctsAgent, PerfectAgent, RandomAgent};
+use monorail::board::{self, Board, Position};
+use monorail::cache;
+use monorail::engine::{Engine, Strength};
+use monorail::game::Game;
+use monorail::heuristic::{AlphaBeta, Weights};
+use monorail::mcts::{self, Mcts};
+use monorail::player::{Player, Players};
+use monorail::reachability;
+use monorail::record;
+use monorail::proof_number::ProofNumberSolver;
+use monorail::solver::{self, GameResult, Limits, Outcome, Search, Solver};
+use monorail::tablebase::{Entry, Tablebase};
 use std::env;
+use std::fs;
 use std::io;
+use std::process;
+use std::thread;
+use std::time::Duration;
 
-#[derive(Copy, Clone, Debug)]
-enum GameResult {
-    JunSeokWin,
-    YeonSeungWin,
+// Settings from the command line that affect how results are shown.
+struct Config {
+    players: Players,
+    colorize: bool,
+    stats: bool,
+    // Where solved positions are kept between runs, if anywhere.
+    cache: Option<String>,
+    // How `-a` and `-b` search.
+    solver: SolverKind,
+    weights: Weights,
+    // When each search gives up, except those that must finish (see the README).
+    limits: Limits,
+    // How long Monte Carlo tree search runs, and the seed for its random moves.
+    mcts_budget: mcts::Budget,
+    seed: u64,
 }
 
-impl GameResult {
-    fn win_for(&self, p: Player) -> bool {
-        match (*self, p) {
-            (GameResult::JunSeokWin, Player::JunSeok) => true,
-            (GameResult::JunSeokWin, _) => false,
-            (GameResult::YeonSeungWin, Player::YeonSeung) => true,
-            (GameResult::YeonSeungWin, _) => false,
-        }
-    }
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum SolverKind {
+    DepthFirst,
+    ProofNumber,
+    Heuristic,
 }
 
-fn game_result(player: Player, board: &mut Board) -> (GameResult, Option<Move>) {
-    let moves = board.legal_moves();
-    // There are no more moves, which means my opponent completed the railroad.
-    // So I lose.
-    if moves.is_empty() {
-        return match player {
-            Player::YeonSeung => (GameResult::JunSeokWin, None),
-            Player::JunSeok => (GameResult::YeonSeungWin, None),
-        }
-    }
-
-    for possible_move in moves.iter() {
-        board.make_move(*possible_move);
-        let (reply, _) = game_result(player.opponent(), board);
-        board.undo_move();
+// The number of positions a proof-number search may look at before giving up, without --node-limit.
+const PROOF_NUMBER_BUDGET: u64 = 1_000_000;
+// How long a heuristic search may look before settling for its best guess, without --time-limit.
+const HEURISTIC_TIME: Duration = Duration::from_secs(5);
 
-        // If I have any move that forces a win, I use that move to win.
-        // We can return early from the search.
-        match (player, reply) {
-            (Player::YeonSeung, GameResult::YeonSeungWin) => return (reply, Some(*possible_move)),
-            (Player::JunSeok, GameResult::JunSeokWin) => return (reply, Some(*possible_move)),
-            _ => (),
-        }
+impl Config {
+    fn heuristic_time(&self) -> Duration {
+        self.limits.time.unwrap_or(HEURISTIC_TIME)
     }
 
-    // I have no move that forces a win, therefore I must have lost.
-    match player {
-        Player::YeonSeung => (GameResult::JunSeokWin, None),
-        Player::JunSeok => (GameResult::YeonSeungWin, None),
+    fn proof_number_budget(&self) -> u64 {
+        self.limits.nodes.unwrap_or(PROOF_NUMBER_BUDGET)
     }
 }
 
-fn print_result(result: GameResult, color: term::color::Color, colorize: bool) {
-    if colorize {
+fn print_result(result: GameResult, config: &Config, color: term::color::Color) {
+    let message = format!("{} wins", config.players.name(result.winner));
+    if config.colorize {
         let mut t = term::stdout().unwrap();
         t.fg(color).unwrap();
         t.attr(term::Attr::Bold).unwrap();
-        writeln!(t, "{:?}", result).unwrap();
+        writeln!(t, "{}", message).unwrap();
         t.reset().unwrap();
     } else {
-        println!("{:?}", result);
+        println!("{}", message);
+    }
+}
+
+// Prints the positions searched since the search had searched `nodes_before`.
+fn print_stats(search: &dyn Search, nodes_before: u64, config: &Config) {
+    if config.stats {
+        println!("Searched {} positions, remembering the winner of {}", search.nodes() - nodes_before, search.positions_remembered());
+    }
+}
+
+// A solver that remembers the positions in the cache, if there is one.
+fn load_solver(config: &Config) -> Solver {
+    let mut solver = Solver::new();
+    if let Some(ref path) = config.cache {
+        // No cache yet is fine; it is written after the first search.
+        if let Ok(contents) = fs::read_to_string(path) {
+            match cache::read(&contents) {
+                Ok(entries) => for (position, winner) in entries {
+                    solver.remember(position, winner);
+                },
+                Err(e) => eprintln!("Ignoring cache {}: {}", path, e),
+            }
+        }
+    }
+    solver
+}
+
+fn save_solver(solver: &Solver, config: &Config) {
+    if let Some(ref path) = config.cache {
+        if let Err(e) = fs::write(path, cache::write(solver)) {
+            eprintln!("Could not save cache {}: {}", path, e);
+        }
+    }
+}
+
+// Reads the cache for the cache commands, saying why if it can't be read.
+fn read_cache(path: &str) -> Option<Vec<(Position, Player)>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Could not read cache {}: {}", path, e);
+            return None;
+        },
+    };
+    match cache::read(&contents) {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+            eprintln!("Invalid cache {}: {}", path, e);
+            None
+        },
+    }
+}
+
+// The cache commands return whether they succeeded, so that the others still run if one fails.
+fn inspect_cache(path: &str, config: &Config) -> bool {
+    let entries = match read_cache(path) {
+        Some(entries) => entries,
+        None => return false,
+    };
+    let wins = entries.iter().filter(|&&(position, winner)| position.to_move() == winner).count();
+    println!("Cache {} for rules version {}: {} positions", path, cache::RULES_VERSION, entries.len());
+    println!("The player to move wins {} of them and loses {}", wins, entries.len() - wins);
+    for &(position, winner) in entries.iter().filter(|_| config.stats) {
+        println!("{} {} to move, {} wins", board::format_position(&position.array(), position.board_type()),
+            config.players.name(position.to_move()), config.players.name(winner));
+    }
+    true
+}
+
+fn verify_cache(path: &str, config: &Config) -> bool {
+    let entries = match read_cache(path) {
+        Some(entries) => entries,
+        None => return false,
+    };
+    let wrong = cache::verify(&entries);
+    for &(position, winner) in wrong.iter() {
+        println!("Wrong: {} {} to move, cached as a win for {}", board::format_position(&position.array(), position.board_type()),
+            config.players.name(position.to_move()), config.players.name(winner));
+    }
+    println!("Verified {} positions, {} wrong", entries.len(), wrong.len());
+    wrong.is_empty()
+}
+
+fn clear_cache(path: &str) -> bool {
+    match fs::remove_file(path) {
+        Ok(()) => println!("Cleared cache {}", path),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => println!("No cache at {}", path),
+        Err(e) => {
+            eprintln!("Could not clear cache {}: {}", path, e);
+            return false;
+        },
+    }
+    true
+}
+
+// Describes a tablebase result from the point of view of `player`.
+fn describe_entry(entry: Entry, player: Player) -> String {
+    let outcome = if entry.winner == player { "wins" } else { "loses" };
+    match entry.distance {
+        1 => format!("{} in 1 move", outcome),
+        n => format!("{} in {} moves", outcome, n),
+    }
+}
+
+fn generate_tablebase(path: &str, position: Position) {
+    let tablebase = Tablebase::generate(position);
+    match fs::write(path, tablebase.to_bytes()) {
+        Ok(()) => println!("Wrote {} positions to {}", tablebase.len(), path),
+        Err(e) => {
+            eprintln!("Could not write tablebase {}: {}", path, e);
+            process::exit(1);
+        },
     }
 }
 
-fn print_all_responses(player: Player, starting_board: &mut Board, colorize: bool) {
+fn read_tablebase(program: &str, path: &str) -> Tablebase {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => usage_error(program, &format!("Could not read tablebase {}: {}", path, e)),
+    };
+    match Tablebase::from_bytes(&bytes) {
+        Ok(tablebase) => tablebase,
+        Err(e) => usage_error(program, &format!("Invalid tablebase {}: {}", path, e)),
+    }
+}
+
+fn print_reachability(start: &Board, first_player: Player, target: &Board, config: &Config) {
+    let witnesses = reachability::reachable(start, first_player, target);
+    if witnesses.is_empty() {
+        println!("Not reachable from the starting position");
+    }
+    for witness in witnesses {
+        let moves: Vec<_> = witness.moves.iter().map(|m| m.notation()).collect();
+        if moves.is_empty() {
+            println!("The starting position, with {} to move", config.players.name(witness.to_move));
+        } else {
+            println!("Reachable in {} moves with {} to move: {}", moves.len(), config.players.name(witness.to_move), moves.join(" "));
+        }
+    }
+}
+
+fn print_all_responses(player: Player, starting_board: &mut Board, search: &mut dyn Search, config: &Config) {
+    let nodes_before = search.nodes();
+    // The limits are shared by all the moves, so once they pass, the rest are unknown.
+    search.set_deadline(config.limits.deadline(nodes_before));
+    let mut every_move_loses = true;
     for legal_move in starting_board.legal_moves().iter() {
-        print!("If {:?} does: {}, ", player, legal_move);
+        print!("If {} does: {}, ", config.players.name(player), legal_move);
         starting_board.make_move(*legal_move);
-        let (result, best_move) = game_result(player.opponent(), starting_board);
-        if result.win_for(player) {
-            print_result(result, term::color::BLUE, colorize);
-            println!("{}", starting_board);
-        } else if let Some(opponent_move) = best_move {
-            print!("{:?} does: {}, ", player.opponent(), opponent_move);
-            print_result(result, term::color::RED, colorize);
-            starting_board.make_move(opponent_move);
-            println!("{}", starting_board);
-            starting_board.undo_move();
-        } else {
-            panic!("no move?");
+        match search.search(starting_board.position(player.opponent())) {
+            Outcome::Proven(result, _) if result.win_for(player) => {
+                every_move_loses = false;
+                print_result(result, config, term::color::BLUE);
+                println!("{}", starting_board);
+            },
+            Outcome::Proven(result, Some(opponent_move)) => {
+                print!("{} does: {}, ", config.players.name(player.opponent()), opponent_move);
+                print_result(result, config, term::color::RED);
+                starting_board.make_move(opponent_move);
+                println!("{}", starting_board);
+                starting_board.undo_move();
+            },
+            Outcome::Proven(_, None) => panic!("no move?"),
+            Outcome::Unknown(guess) => {
+                every_move_loses = false;
+                match guess {
+                    Some(opponent_move) => println!("{} might do: {}, result unknown", config.players.name(player.opponent()), opponent_move),
+                    None => println!("result unknown"),
+                }
+                println!("{}", starting_board);
+            },
         }
         starting_board.undo_move();
     }
+    if every_move_loses && !starting_board.legal_moves().is_empty() {
+        print_practical_chances(player, starting_board, search, config);
+    }
+    print_stats(search, nodes_before, config);
+}
+
+// Ranks the moves of a lost position by how likely a random reply is to let `player` win anyway.
+fn print_practical_chances(player: Player, starting_board: &mut Board, search: &mut dyn Search, config: &Config) {
+    let (chances, unranked) = solver::practical_chances(search, starting_board.position(player));
+    println!("Every move loses. Chances against a random reply, best first:");
+    for c in chances.iter() {
+        println!("{:>3} of {:>3} replies win for {}: {}", c.winning_replies, c.replies, config.players.name(player), c.mv);
+    }
+    for m in unranked.iter() {
+        println!("Not ranked, a reply could not be solved in time: {}", m);
+    }
+    match chances.first() {
+        Some(best) if best.winning_replies > 0 => {
+            println!("Best swindle: {}", best.mv);
+            starting_board.make_move(best.mv);
+            println!("{}", starting_board);
+            starting_board.undo_move();
+        },
+        Some(_) => println!("No move gives {} a chance.", config.players.name(player)),
+        None => println!("The moves could not be ranked in time."),
+    }
+}
+
+fn print_best_move(player: Player, starting_board: &mut Board, search: &mut dyn Search, config: &Config) {
+    let nodes_before = search.nodes();
+    search.set_deadline(config.limits.deadline(nodes_before));
+    match search.search(starting_board.position(player)) {
+        Outcome::Proven(result, best_move) => {
+            println!("{} wins", config.players.name(result.winner));
+            match best_move {
+                Some(x) => {
+                    println!("{}", x);
+                    starting_board.make_move(x);
+                    println!("{}", starting_board);
+                    starting_board.undo_move();
+                },
+                None if !starting_board.legal_moves().is_empty() => print_practical_chances(player, starting_board, search, config),
+                None => println!("No move"),
+            }
+        },
+        Outcome::Unknown(guess) => {
+            println!("Result unknown");
+            if let Some(x) = guess {
+                println!("Best guess: {}", x);
+                starting_board.make_move(x);
+                println!("{}", starting_board);
+                starting_board.undo_move();
+            }
+        },
+    }
+    print_stats(search, nodes_before, config);
 }
 
-fn print_best_move(player: Player, starting_board: &mut Board) {
-    let (result, best_move) = game_result(player, starting_board);
-    println!("{:?}", result);
-    match best_move {
+fn print_mcts(starting_board: &mut Board, config: &Config) {
+    let mut mcts = Mcts::new(config.seed);
+    mcts.set_deadline(config.limits.deadline(0));
+    let report = mcts.search(starting_board, config.mcts_budget);
+    println!("After {} iterations of Monte Carlo tree search:", report.iterations);
+    for stats in report.moves.iter() {
+        println!("{:>8} visits, {:5.1}% won: {}", stats.visits, 100.0 * stats.win_rate(), stats.mv);
+    }
+    match report.best {
         Some(x) => {
-            println!("{}", x);
+            println!("Suggested move: {}", x);
             starting_board.make_move(x);
             println!("{}", starting_board);
             starting_board.undo_move();
@@ -103,40 +324,337 @@ fn print_best_move(player: Player, starting_board: &mut Board) {
     }
 }
 
+// The agents that `--match` can match up.
+const AGENTS: [&str; 6] = ["perfect", "random", "heuristic", "mcts", "engine", "human"];
+
+// The agent called `name`, seeding its random choices (if any) with `seed`.
+fn make_agent(name: &str, seed: u64, strength: Option<Strength>, config: &Config) -> Box<dyn Agent> {
+    match name {
+        "perfect" => Box::new(PerfectAgent::new(config.limits)),
+        "random" => Box::new(RandomAgent::new(seed)),
+        "heuristic" => Box::new(HeuristicAgent::new(config.weights, config.limits, config.heuristic_time())),
+        "mcts" => Box::new(MctsAgent::new(seed, config.mcts_budget, config.limits)),
+        "engine" => Box::new(match strength {
+            Some(strength) => Engine::fallible(config.weights, strength, config.limits, seed),
+            None => Engine::new(config.weights, config.limits, config.heuristic_time()),
+        }),
+        "human" => Box::new(HumanAgent::new(io::stdin().lock())),
+        _ => unreachable!("agent names are checked when parsing options"),
+    }
+}
+
+// Plays `games` games between two agents, showing the moves of a single game, or else the number of wins.
+fn play_match(starting_board: &Board, starting_player: Player, agents: &mut [Box<dyn Agent>; 2], names: &[String; 2], games: u64, config: &Config) {
+    let (mut wins, mut played) = ([0, 0], 0);
+    for _ in 0..games {
+        let [ref mut one, ref mut two] = *agents;
+        let game = agent::play_game(starting_board.clone(), starting_player, one.as_mut(), two.as_mut());
+        if games == 1 {
+            print_history(&game, &config.players);
+            println!("{}", game.board());
+        }
+        match game.outcome() {
+            Some(Player::One) => wins[0] += 1,
+            Some(Player::Two) => wins[1] += 1,
+            None => {
+                println!("The game was given up.");
+                break;
+            },
+        }
+        played += 1;
+    }
+    for (i, &player) in [Player::One, Player::Two].iter().enumerate() {
+        println!("{} ({}) won {} of {}", config.players.name(player), names[i], wins[i], played);
+    }
+}
+
+// The searches that `-a` and `-b` can use.
+struct Searches {
+    solver: Solver,
+    proof_number: ProofNumberSolver,
+    heuristic: AlphaBeta,
+}
+
+impl Searches {
+    // The search chosen on the command line.
+    fn chosen(&mut self, config: &Config) -> &mut dyn Search {
+        match config.solver {
+            SolverKind::DepthFirst => &mut self.solver,
+            SolverKind::ProofNumber => &mut self.proof_number,
+            SolverKind::Heuristic => &mut self.heuristic,
+        }
+    }
+}
+
+// Prints the numbered moves of the game, marking the current position.
+fn print_history(game: &Game, players: &Players) {
+    let mut player = game.first_player();
+    for (i, m) in game.history().iter().enumerate() {
+        let marker = if i + 1 == game.ply() { " <--" } else { "" };
+        println!("{} {}: {}{}", i + 1, players.name(player), m, marker);
+        player = player.opponent();
+    }
+}
+
+// Prints the moves explored from the current position.
+fn print_variations(game: &Game) {
+    let variations = game.variations();
+    if variations.is_empty() {
+        println!("No variations from here.");
+    }
+    for (i, m) in variations.iter().enumerate() {
+        let marker = if i == 0 { " (main line)" } else { "" };
+        println!("{} {}{}", i, m, marker);
+    }
+}
+
+fn print_usage(program: &str, opts: &Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+// Reports a command-line error and exits, pointing the user to --help.
+fn usage_error(program: &str, message: &str) -> ! {
+    eprintln!("{}: {}", program, message);
+    eprintln!("Try '{} --help' for more information.", program);
+    process::exit(2);
+}
+
 fn main() {
-    let mut starting_board = Board::new(
-        [
-            [false,  true,  true,  true, false],
-            [false, false, false,  true, false],
-            [false, false, false,  true, false],
-            [false, false, false, false, false],
-        ],
-        None,
-    );
-    let starting_player = Player::YeonSeung;
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag("b", "best", "print the best move of the player to move and the result under optimal play");
+    opts.optflag("a", "analyze", "print the opponent's best response to every move of the player to move");
+    opts.optflag("l", "list", "list all legal moves of the player to move");
+    opts.optflag("c", "color", "colorize output");
+    opts.optflag("s", "stats", "print the number of positions searched by -a and -b");
+    opts.optopt("", "solver", "search with SOLVER: depth-first (the default), proof-number, or heuristic", "SOLVER");
+    opts.optopt("", "weights", "weigh parity, frontier squares and squares left by these in heuristic search (4,-1,1 by default)", "P,F,S");
+    opts.optopt("", "time-limit", "stop each search after SECONDS, answering with what it found so far", "SECONDS");
+    opts.optopt("", "node-limit", "stop each search after searching N positions", "N");
+    opts.optmulti("", "computer", "let the engine play PLAYER (by number or name) at the prompt; give twice for both players", "PLAYER");
+    opts.optopt("", "error-rate", "let the engine play a worse move than the best with chance P, from 0 to 1", "P");
+    opts.optopt("", "engine-depth", "let the engine look only N moves ahead, judging the positions there with the heuristic", "N");
+    opts.optopt("", "match", "play a game between AGENT1 and AGENT2, each perfect, random, heuristic, mcts, engine, or human", "AGENT1,AGENT2");
+    opts.optopt("", "games", "play N games with --match, counting the wins (1 by default)", "N");
+    opts.optopt("", "delay", "wait SECONDS before each of the engine's moves", "SECONDS");
+    opts.optflag("m", "mcts", "suggest a move for the player to move by Monte Carlo tree search");
+    opts.optopt("", "iterations", "run Monte Carlo tree search for N iterations (10000 by default)", "N");
+    opts.optopt("", "mcts-time", "run Monte Carlo tree search for SECONDS instead", "SECONDS");
+    opts.optopt("", "seed", "seed the random moves of Monte Carlo tree search and the engine's mistakes with N (0 by default)", "N");
+    opts.optopt("", "cache", "remember solved positions in FILE between runs", "FILE");
+    opts.optflag("", "inspect-cache", "summarize the cache given by --cache, listing its positions with -s");
+    opts.optflag("", "verify-cache", "solve every position in the cache again and report wrong winners");
+    opts.optflag("", "clear-cache", "delete the cache given by --cache");
+    opts.optopt("p", "position", "start from POSITION instead of the season 4 board", "POSITION");
+    opts.optopt("n", "names", "display names of the two players", "NAME1,NAME2");
+    opts.optopt("f", "first", "player to move first, by number (1 or 2) or by name", "PLAYER");
+    opts.optopt("", "reachable", "check whether POSITION can arise from the starting position, and how", "POSITION");
+    opts.optopt("", "generate-tablebase", "solve every position reachable from the starting position and write them to FILE", "FILE");
+    opts.optopt("t", "tablebase", "show results from the tablebase in FILE at the prompt", "FILE");
+    opts.optflag("h", "help", "print this help and exit");
+    opts.optflag("V", "version", "print version information and exit");
 
-    let mut all_responses = false;
-    let mut best_move = false;
-    let mut legal_moves = false;
-    let mut colorize = false;
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => usage_error(&program, &f.to_string()),
+    };
+    if let Some(arg) = matches.free.first() {
+        usage_error(&program, &format!("Unexpected argument '{}'", arg));
+    }
+
+    if matches.opt_present("h") {
+        print_usage(&program, &opts);
+        return;
+    }
+    if matches.opt_present("V") {
+        println!("monorail {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    let mut starting_board = match matches.opt_str("p") {
+        Some(position) => match board::parse_position(&position) {
+            Ok((array, board_type)) => match Board::try_new(array, board_type) {
+                Ok(board) => board,
+                Err(e) => usage_error(&program, &format!("Invalid position: {}", e)),
+            },
+            Err(e) => usage_error(&program, &format!("Invalid position: {}", e)),
+        },
+        None => Board::new(
+            [
+                [false,  true,  true,  true, false],
+                [false, false, false,  true, false],
+                [false, false, false,  true, false],
+                [false, false, false, false, false],
+            ],
+            None,
+        ),
+    };
+    let players = match matches.opt_str("n") {
+        Some(names) => match names.parse::<Players>() {
+            Ok(players) => players,
+            Err(e) => usage_error(&program, &e),
+        },
+        None => Players::default(),
+    };
+    let starting_player = match matches.opt_str("f") {
+        Some(player) => match players.find(&player) {
+            Some(p) => p,
+            None => usage_error(&program, &format!("Unknown player {}", player)),
+        },
+        None => Player::One,
+    };
+
+    let mcts_budget = match (matches.opt_str("iterations"), matches.opt_str("mcts-time")) {
+        (Some(_), Some(_)) => usage_error(&program, "Give either --iterations or --mcts-time, not both"),
+        (Some(n), None) => match n.parse() {
+            Ok(n) => mcts::Budget::Iterations(n),
+            Err(_) => usage_error(&program, &format!("Invalid number of iterations {}", n)),
+        },
+        (None, Some(seconds)) => match seconds.parse::<f64>() {
+            Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => mcts::Budget::Time(Duration::from_secs_f64(seconds)),
+            _ => usage_error(&program, &format!("Invalid time {}", seconds)),
+        },
+        (None, None) => mcts::Budget::Iterations(10000),
+    };
+    let seed = match matches.opt_str("seed") {
+        Some(seed) => match seed.parse() {
+            Ok(seed) => seed,
+            Err(_) => usage_error(&program, &format!("Invalid seed {}", seed)),
+        },
+        None => 0,
+    };
+
+    let limits = Limits {
+        time: matches.opt_str("time-limit").map(|seconds| match seconds.parse::<f64>() {
+            Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => Duration::from_secs_f64(seconds),
+            _ => usage_error(&program, &format!("Invalid time limit {}", seconds)),
+        }),
+        nodes: matches.opt_str("node-limit").map(|n| match n.parse() {
+            Ok(n) => n,
+            Err(_) => usage_error(&program, &format!("Invalid node limit {}", n)),
+        }),
+    };
 
-    for argument in env::args() {
-        if argument == "-b" {
-            best_move = true;
+    let computers: Vec<Player> = matches.opt_strs("computer").iter().map(|player| match players.find(player) {
+        Some(p) => p,
+        None => usage_error(&program, &format!("Unknown player {}", player)),
+    }).collect();
+    let error_rate = matches.opt_str("error-rate").map(|p| match p.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => p,
+        _ => usage_error(&program, &format!("Invalid error rate {}", p)),
+    });
+    let engine_depth = matches.opt_str("engine-depth").map(|n| match n.parse::<usize>() {
+        Ok(n) if n > 0 => n,
+        _ => usage_error(&program, &format!("Invalid engine depth {}", n)),
+    });
+    // Without either, the engine is as strong as it can be.
+    let strength = match (error_rate, engine_depth) {
+        (None, None) => None,
+        (error_rate, depth) => Some(Strength { error_rate: error_rate.unwrap_or(0.0), depth }),
+    };
+    let match_agents = matches.opt_str("match").map(|agents| {
+        let agents: Vec<_> = agents.split(',').map(|agent| agent.trim().to_string()).collect();
+        if agents.len() != 2 || agents.iter().any(|agent| !AGENTS.contains(&agent.as_str())) {
+            usage_error(&program, &format!("Expected two comma-separated agents for --match, each one of {}", AGENTS.join(", ")));
         }
-        if argument == "-a" {
-            all_responses = true;
+        if agents.iter().all(|agent| agent == "human") {
+            usage_error(&program, "Two people can play each other at the prompt, without --match");
         }
-        if argument == "-l" {
-            legal_moves = true;
+        [agents[0].clone(), agents[1].clone()]
+    });
+    let games = match matches.opt_str("games") {
+        Some(n) => match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => usage_error(&program, &format!("Invalid number of games {}", n)),
+        },
+        None => 1,
+    };
+    let delay = match matches.opt_str("delay") {
+        Some(seconds) => match seconds.parse::<f64>() {
+            Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => Duration::from_secs_f64(seconds),
+            _ => usage_error(&program, &format!("Invalid delay {}", seconds)),
+        },
+        None => Duration::from_secs(0),
+    };
+
+    let weights = match matches.opt_str("weights") {
+        Some(weights) => {
+            let weights: Vec<_> = weights.split(',').map(|w| w.trim().parse::<i32>()).collect();
+            match weights.as_slice() {
+                [Ok(parity), Ok(frontier), Ok(squares_left)] => Weights { parity: *parity, frontier: *frontier, squares_left: *squares_left },
+                _ => usage_error(&program, "Expected three comma-separated whole numbers for --weights"),
+            }
+        },
+        None => Weights::default(),
+    };
+
+    let all_responses = matches.opt_present("a");
+    let monte_carlo = matches.opt_present("m");
+    let best_move = matches.opt_present("b");
+    let legal_moves = matches.opt_present("l");
+    let config = Config {
+        players,
+        colorize: matches.opt_present("c"),
+        stats: matches.opt_present("s"),
+        cache: matches.opt_str("cache"),
+        solver: match matches.opt_str("solver").as_deref() {
+            None | Some("depth-first") => SolverKind::DepthFirst,
+            Some("proof-number") => SolverKind::ProofNumber,
+            Some("heuristic") => SolverKind::Heuristic,
+            Some(other) => usage_error(&program, &format!("Unknown solver {}", other)),
+        },
+        weights,
+        limits,
+        mcts_budget,
+        seed,
+    };
+
+    let cache_commands = ["inspect-cache", "verify-cache", "clear-cache"];
+    let cache_command = cache_commands.iter().any(|c| matches.opt_present(c));
+    if cache_command {
+        let path = match config.cache {
+            Some(ref path) => path.clone(),
+            None => usage_error(&program, "The cache commands need --cache FILE"),
+        };
+        // Clearing comes last, after the cache has been looked at, even if that found a problem.
+        let mut ok = true;
+        if matches.opt_present("inspect-cache") {
+            ok &= inspect_cache(&path, &config);
+        }
+        if matches.opt_present("verify-cache") {
+            ok &= verify_cache(&path, &config);
+        }
+        if matches.opt_present("clear-cache") {
+            ok &= clear_cache(&path);
         }
-        if argument == "-c" {
-            colorize = true;
+        if !ok {
+            process::exit(1);
+        }
+    }
+
+    let reachable = matches.opt_str("reachable");
+    if let Some(ref position) = reachable {
+        match board::parse_position(position) {
+            Ok((array, board_type)) => print_reachability(&starting_board, starting_player, &Board::new(array, board_type), &config),
+            Err(e) => usage_error(&program, &format!("Invalid position: {}", e)),
         }
     }
 
-    let interactive = !all_responses && !best_move && !legal_moves;
+    let generate = matches.opt_str("generate-tablebase");
+    if let Some(ref path) = generate {
+        generate_tablebase(path, starting_board.position(starting_player));
+    }
+    let tablebase = matches.opt_str("t").map(|path| read_tablebase(&program, &path));
 
+    let mut searches = Searches {
+        solver: load_solver(&config),
+        proof_number: ProofNumberSolver::new(config.proof_number_budget()),
+        heuristic: AlphaBeta::new(config.weights, config.heuristic_time()),
+    };
+    let interactive = !all_responses && !best_move && !monte_carlo && !legal_moves && !cache_command && reachable.is_none() && generate.is_none() && match_agents.is_none();
     if legal_moves {
         for legal_move in starting_board.legal_moves().iter() {
             println!("{}", legal_move);
@@ -144,52 +662,172 @@ fn main() {
     }
 
     if best_move {
-        print_best_move(starting_player, &mut starting_board);
+        print_best_move(starting_player, &mut starting_board, searches.chosen(&config), &config);
+        save_solver(&searches.solver, &config);
+    }
+
+    if monte_carlo {
+        print_mcts(&mut starting_board, &config);
+    }
+
+    if let Some(ref names) = match_agents {
+        // Different seeds, so that two random agents don't mirror each other.
+        let mut agents = [make_agent(&names[0], config.seed, strength, &config), make_agent(&names[1], config.seed.wrapping_add(1), strength, &config)];
+        play_match(&starting_board, starting_player, &mut agents, names, games, &config);
     }
 
     if all_responses {
-        print_all_responses(starting_player, &mut starting_board, colorize);
+        print_all_responses(starting_player, &mut starting_board, searches.chosen(&config), &config);
+        save_solver(&searches.solver, &config);
     }
 
     if interactive {
-        let mut player = starting_player;
-        let mut turn_counter = 1;
+        let mut game = Game::new(starting_board, starting_player);
+        // The engine playing each computer player, seeded differently so that they don't mirror each other.
+        let mut computer_agents: Vec<(Player, Box<dyn Agent>)> = computers.iter().map(|&player| {
+            let seed = if player == Player::One { config.seed } else { config.seed.wrapping_add(1) };
+            (player, make_agent("engine", seed, strength, &config))
+        }).collect();
+        // After moving around the game, the engine waits to be told to move,
+        // so that undoing its move doesn't just make it play again.
+        let mut engine_waiting = false;
         loop {
-            println!("=================== Turn {} ===================", turn_counter);
-            let moves = starting_board.legal_moves();
-            if moves.is_empty() {
-                println!("No moves left, {:?} wins", player.opponent());
-                break;
+            println!("=================== Turn {} ===================", game.turn());
+            let moves = game.legal_moves();
+            println!("{}", game.board());
+            if let Some(winner) = game.outcome() {
+                println!("No moves left, {} wins", config.players.name(winner));
+            } else if !engine_waiting {
+                if let Some(computer) = computer_agents.iter_mut().find(|c| c.0 == game.player()) {
+                    thread::sleep(delay);
+                    let m = computer.1.choose(game.board()).expect("the game isn't over");
+                    println!("{} plays: {}", config.players.name(game.player()), m);
+                    game.play(m).expect("the engine's moves are legal");
+                    continue;
+                }
             }
-            println!("{}", starting_board);
             for (i, legal_move) in moves.iter().enumerate() {
-                println!("{} {}", i, legal_move);
+                let after = game.board().position(game.player()).apply(*legal_move);
+                match tablebase.as_ref().and_then(|t| t.query(after)) {
+                    // Counting the move itself.
+                    Some(entry) => println!("{} {} ({})", i, legal_move, describe_entry(Entry { distance: entry.distance + 1, .. entry }, game.player())),
+                    None => println!("{} {}", i, legal_move),
+                }
+            }
+            if game.outcome().is_none() {
+                if let Some(entry) = tablebase.as_ref().and_then(|t| t.query(game.board().position(game.player()))) {
+                    println!("Tablebase: {} {}", config.players.name(game.player()), describe_entry(entry, game.player()));
+                }
+                println!("It's {}'s turn. What move?", config.players.name(game.player()));
+                if computers.contains(&game.player()) {
+                    println!("(Enter engine to let the engine move.)");
+                }
             }
-            println!("It's {:?}'s turn. What move?", player);
             let mut input_move = String::new();
-            io::stdin().read_line(&mut input_move).ok().expect("Failed to read line");
+            if io::stdin().read_line(&mut input_move).expect("Failed to read line") == 0 {
+                break;
+            }
             let input_move = input_move.trim();
-            if input_move == "analyze" || input_move == "a" {
-                print_all_responses(player, &mut starting_board, colorize);
-            } else if input_move == "best" || input_move == "b" {
-                print_best_move(player, &mut starting_board);
-            } else if input_move == "undo" || input_move == "u" {
-                if let Some(_) = starting_board.undo_move() {
-                    player = player.opponent();
-                    turn_counter -= 1;
+            let mut words = input_move.split_whitespace();
+            let command = words.next().unwrap_or("");
+            // Only a move played here, or the engine command, lets the engine move next.
+            engine_waiting = true;
+            if command == "quit" || command == "q" {
+                break;
+            } else if command == "engine" {
+                engine_waiting = false;
+            } else if command == "analyze" || command == "a" {
+                print_all_responses(game.player(), &mut game.board().clone(), searches.chosen(&config), &config);
+                save_solver(&searches.solver, &config);
+            } else if command == "best" || command == "b" {
+                let position = game.board().position(game.player());
+                match tablebase.as_ref().and_then(|t| Some((t.query(position)?, t.best_move(position)?))) {
+                    Some((entry, m)) => {
+                        println!("Tablebase: {} {}", config.players.name(game.player()), describe_entry(entry, game.player()));
+                        println!("{}", m);
+                    },
+                    None => {
+                        print_best_move(game.player(), &mut game.board().clone(), searches.chosen(&config), &config);
+                        save_solver(&searches.solver, &config);
+                    },
+                }
+            } else if command == "mcts" || command == "m" {
+                print_mcts(&mut game.board().clone(), &config);
+            } else if command == "undo" || command == "u" {
+                if game.undo().is_none() {
+                    println!("Nothing to undo.");
+                }
+            } else if command == "redo" || command == "r" {
+                if game.redo().is_none() {
+                    println!("Nothing to redo.");
+                }
+            } else if command == "history" || command == "h" {
+                print_history(&game, &config.players);
+            } else if command == "variations" || command == "v" {
+                match words.next().map(|w| w.parse::<usize>()) {
+                    None => print_variations(&game),
+                    Some(Ok(i)) => match game.variations().get(i) {
+                        Some(&m) => game.play(m).expect("explored moves are legal"),
+                        None => println!("Variation not found."),
+                    },
+                    Some(Err(_)) => println!("Usage: variations [N]"),
+                }
+            } else if command == "promote" {
+                match words.next().map(|w| w.parse()) {
+                    Some(Ok(i)) => if !game.promote_variation(i) {
+                        println!("Variation not found.");
+                    },
+                    _ => println!("Usage: promote N"),
+                }
+            } else if command == "delete" {
+                match words.next().map(|w| w.parse()) {
+                    Some(Ok(i)) => if game.delete_variation(i).is_none() {
+                        println!("Variation not found.");
+                    },
+                    _ => println!("Usage: delete N"),
+                }
+            } else if command == "save" {
+                match words.next() {
+                    Some(path) => if let Err(e) = fs::write(path, record::write(&game)) {
+                        println!("Could not save to {}: {}", path, e);
+                    },
+                    None => println!("Usage: save FILE"),
+                }
+            } else if command == "load" {
+                match words.next() {
+                    Some(path) => match fs::read_to_string(path) {
+                        Ok(contents) => match record::read(&contents) {
+                            Ok(loaded) => game = loaded,
+                            Err(e) => println!("Could not load {}: {}", path, e),
+                        },
+                        Err(e) => println!("Could not load {}: {}", path, e),
+                    },
+                    None => println!("Usage: load FILE"),
+                }
+            } else if command == "explain" || command == "e" {
+                match words.next().map(|w| w.parse::<Move>()) {
+                    Some(Ok(m)) => match game.board().check_move(m) {
+                        Ok(()) => println!("{} is legal.", m.notation()),
+                        Err(e) => println!("{} is illegal: {}.", m.notation(), e),
+                    },
+                    Some(Err(e)) => println!("Not a move: {}.", e),
+                    None => println!("Usage: explain MOVE, such as explain OneRight@3,0"),
+                }
+            } else if command == "goto" || command == "g" {
+                match words.next().map(|w| w.parse()) {
+                    Some(Ok(ply)) => if !game.goto(ply) {
+                        println!("The history only has {} moves.", game.history().len());
+                    },
+                    _ => println!("Usage: goto N, where N is the number of moves to have made."),
                 }
             } else {
-                let input_move: usize = match input_move.trim().parse() {
-                    Ok(num) => num,
-                    Err(_) => { println!("Not a number."); continue },
-                };
-                match moves.get(input_move) {
-                    Some(legal_move) => {
-                        starting_board.make_move(*legal_move);
-                        player = player.opponent();
-                        turn_counter += 1;
+                // Anything else should be a move, read as a human agent reads it.
+                match agent::read_move(game.board(), &moves, input_move) {
+                    Ok(m) => {
+                        game.play(m).expect("moves read from the board are legal");
+                        engine_waiting = false;
                     },
-                    None => println!("Move not found.")
+                    Err(e) => println!("{}", e),
                 }
             }
         }
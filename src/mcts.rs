@@ -0,0 +1,196 @@
+// Monte Carlo tree search suggests moves in positions too large to solve.
+// Each iteration follows the most promising moves down the tree it has built so far,
+// adds one new position to it, and plays random moves from there to the end of the game.
+// The result is credited to every move on the way, so that moves that win more are tried more.
+
+use action::Move;
+use board::Board;
+use random::Rng;
+use solver::Deadline;
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+// How long a search runs.
+#[derive(Copy, Clone, Debug)]
+pub enum Budget {
+    Iterations(u64),
+    Time(Duration),
+}
+
+// What the search found out about one of the moves at the root.
+#[derive(Copy, Clone, Debug)]
+pub struct MoveStats {
+    pub mv: Move,
+    pub visits: u64,
+    // The number of visits that the player making the move went on to win.
+    pub wins: u64,
+}
+
+impl MoveStats {
+    pub fn win_rate(&self) -> f64 {
+        if self.visits == 0 { 0.0 } else { self.wins as f64 / self.visits as f64 }
+    }
+}
+
+pub struct Report {
+    // The most visited move, if there are any moves.
+    pub best: Option<Move>,
+    // Every move at the root, most visited first.
+    pub moves: Vec<MoveStats>,
+    pub iterations: u64,
+}
+
+struct Node {
+    mv: Option<Move>,
+    children: Vec<usize>,
+    // Moves not yet added as children.
+    untried: Vec<Move>,
+    visits: u64,
+    // Visits won by the player who made `mv`.
+    wins: u64,
+}
+
+pub struct Mcts {
+    rng: Rng,
+    // How much to favor moves tried less often over moves winning more often.
+    exploration: f64,
+    // Stops searches before the budget, counting each iteration as one position searched,
+    // since each adds one position to the tree.
+    deadline: Deadline,
+}
+
+impl Mcts {
+    pub fn new(seed: u64) -> Mcts {
+        Mcts { rng: Rng::new(seed), exploration: 2f64.sqrt(), deadline: Deadline::default() }
+    }
+
+    // Makes searches stop at `deadline` if their budget hasn't run out first.
+    // Each search counts its iterations from 0.
+    pub fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    // Searches the position on `board`, leaving the board as it was.
+    pub fn search(&mut self, board: &mut Board, budget: Budget) -> Report {
+        let mut tree = vec![Node { mv: None, children: Vec::new(), untried: board.legal_moves(), visits: 0, wins: 0 }];
+        let start = Instant::now();
+        let mut iterations = 0;
+        while match budget {
+            Budget::Iterations(n) => iterations < n,
+            Budget::Time(limit) => start.elapsed() < limit,
+        } && !self.deadline.passed(iterations) {
+            self.iterate(board, &mut tree);
+            iterations += 1;
+        }
+
+        let mut moves: Vec<_> = tree[0].children.iter().map(|&c| MoveStats {
+            mv: tree[c].mv.expect("every child has a move"),
+            visits: tree[c].visits,
+            wins: tree[c].wins,
+        }).collect();
+        moves.extend(tree[0].untried.iter().map(|&mv| MoveStats { mv, visits: 0, wins: 0 }));
+        moves.sort_by_key(|stats| Reverse(stats.visits));
+        Report { best: moves.first().map(|stats| stats.mv), moves, iterations }
+    }
+
+    fn iterate(&mut self, board: &mut Board, tree: &mut Vec<Node>) {
+        // Selection: follow the most promising moves while every move has been tried.
+        let mut path = vec![0];
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            node = self.select(tree, node);
+            board.make_move(tree[node].mv.expect("every child has a move"));
+            path.push(node);
+        }
+
+        // Expansion: add one untried move.
+        if !tree[node].untried.is_empty() {
+            let i = self.rng.below(tree[node].untried.len());
+            let mv = tree[node].untried.swap_remove(i);
+            board.make_move(mv);
+            tree.push(Node { mv: Some(mv), children: Vec::new(), untried: board.legal_moves(), visits: 0, wins: 0 });
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            path.push(child);
+        }
+
+        // Simulation: play random moves until the game is over.
+        let mut random_moves = 0;
+        loop {
+            let moves = board.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            board.make_move(moves[self.rng.below(moves.len())]);
+            random_moves += 1;
+        }
+
+        // Backpropagation: the player left without a move lost, so whoever made the last move won,
+        // along with every move made by the same player.
+        let end = path.len() - 1 + random_moves;
+        for (depth, &i) in path.iter().enumerate() {
+            tree[i].visits += 1;
+            if depth > 0 && depth % 2 == end % 2 {
+                tree[i].wins += 1;
+            }
+        }
+        for _ in 0..end {
+            board.undo_move();
+        }
+    }
+
+    // The child with the highest upper confidence bound on its win rate.
+    fn select(&self, tree: &[Node], node: usize) -> usize {
+        let log_visits = (tree[node].visits as f64).ln();
+        let bound = |c: usize| {
+            let child = &tree[c];
+            child.wins as f64 / child.visits as f64 + self.exploration * (log_visits / child.visits as f64).sqrt()
+        };
+        *tree[node].children.iter().max_by(|&&a, &&b| bound(a).partial_cmp(&bound(b)).expect("bounds are numbers"))
+            .expect("the node has children")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, Mcts};
+    use board::{self, Board};
+    use solver::Limits;
+
+    fn board(position: &str) -> Board {
+        let (array, board_type) = board::parse_position(position).unwrap();
+        Board::new(array, board_type)
+    }
+
+    #[test]
+    fn same_seed_same_report() {
+        let mut start = board(".###./...#./...#./.....");
+        let a = Mcts::new(1).search(&mut start, Budget::Iterations(500));
+        let b = Mcts::new(1).search(&mut start, Budget::Iterations(500));
+        assert_eq!(a.best, b.best);
+        assert_eq!(a.moves.iter().map(|s| (s.visits, s.wins)).collect::<Vec<_>>(), b.moves.iter().map(|s| (s.visits, s.wins)).collect::<Vec<_>>());
+        assert_eq!(a.iterations, 500);
+        assert_eq!(a.moves.iter().map(|s| s.visits).sum::<u64>(), 500);
+        assert_eq!(a.moves.len(), start.legal_moves().len());
+        assert!(start.moves_made().is_empty());
+    }
+
+    #[test]
+    fn finds_the_winning_move() {
+        // Placing (row 3, col 0) and (row 3, col 1) together finishes the railroad;
+        // placing only one of them lets the opponent finish it.
+        let mut nearly_done = board("#####/#.###/#.###/..###:Left");
+        let report = Mcts::new(3).search(&mut nearly_done, Budget::Iterations(200));
+        let best = report.best.unwrap();
+        assert_eq!(best.squares().len(), 2);
+        assert_eq!(report.moves[0].win_rate(), 1.0);
+    }
+
+    #[test]
+    fn stops_at_the_deadline() {
+        let mut start = board(".###./...#./...#./.....");
+        let mut mcts = Mcts::new(1);
+        mcts.set_deadline(Limits { time: None, nodes: Some(50) }.deadline(0));
+        assert_eq!(mcts.search(&mut start, Budget::Iterations(500)).iterations, 50);
+    }
+}
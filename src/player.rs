@@ -1,15 +1,124 @@
-#[derive(Copy, Clone, Debug)]
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum Player {
-    YeonSeung,
-    JunSeok,
+    One,
+    Two,
 }
 
 impl Player {
     pub fn opponent(&self) -> Player {
         match *self {
-            Player::YeonSeung => Player::JunSeok,
-            Player::JunSeok => Player::YeonSeung,
+            Player::One => Player::Two,
+            Player::Two => Player::One,
+        }
+    }
+}
+
+// Display names for the two players.
+pub struct Players {
+    names: [String; 2],
+}
+
+impl Players {
+    pub fn new(one: String, two: String) -> Players {
+        Players {
+            names: [one, two],
+        }
+    }
+
+    pub fn name(&self, p: Player) -> &str {
+        match p {
+            Player::One => &self.names[0],
+            Player::Two => &self.names[1],
         }
     }
+
+    // Finds a player either by number ("1" or "2") or by name, ignoring case.
+    pub fn find(&self, s: &str) -> Option<Player> {
+        [Player::One, Player::Two].iter().cloned().find(|&p| {
+            let number = match p {
+                Player::One => "1",
+                Player::Two => "2",
+            };
+            s == number || self.name(p).eq_ignore_ascii_case(s)
+        })
+    }
 }
 
+impl Default for Players {
+    fn default() -> Players {
+        Players::new("Player 1".to_string(), "Player 2".to_string())
+    }
+}
+
+// Parses the two names given to --names, such as "Alice,Bob".
+// `find` must tell the players apart, so the names can't be player numbers or the same but for case.
+impl FromStr for Players {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Players, String> {
+        let names: Vec<_> = s.split(',').map(|name| name.trim().to_string()).collect();
+        if names.len() != 2 || names.iter().any(|name| name.is_empty()) {
+            return Err("Expected two comma-separated names".to_string());
+        }
+        if let Some(name) = names.iter().find(|&name| name == "1" || name == "2") {
+            return Err(format!("The name {} would be mistaken for a player number", name));
+        }
+        if names[0].eq_ignore_ascii_case(&names[1]) {
+            return Err(format!("The names {} and {} can't be told apart", names[0], names[1]));
+        }
+        Ok(Players::new(names[0].clone(), names[1].clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Player, Players};
+
+    #[test]
+    fn finds_players_by_number() {
+        let players = Players::default();
+        assert_eq!(players.find("1"), Some(Player::One));
+        assert_eq!(players.find("2"), Some(Player::Two));
+        assert_eq!(players.find("3"), None);
+    }
+
+    #[test]
+    fn finds_players_by_name_ignoring_case() {
+        let players: Players = "Alice, Bob".parse().unwrap();
+        assert_eq!(players.name(Player::One), "Alice");
+        assert_eq!(players.find("alice"), Some(Player::One));
+        assert_eq!(players.find("BOB"), Some(Player::Two));
+        assert_eq!(players.find("2"), Some(Player::Two));
+        assert_eq!(Players::default().find("player 2"), Some(Player::Two));
+    }
+
+    #[test]
+    fn unknown_names_are_not_found() {
+        let players: Players = "Alice,Bob".parse().unwrap();
+        assert_eq!(players.find("Carol"), None);
+        assert_eq!(players.find(""), None);
+    }
+
+    #[test]
+    fn names_must_be_two_and_nonempty() {
+        assert!("Alice".parse::<Players>().is_err());
+        assert!("Alice,Bob,Carol".parse::<Players>().is_err());
+        assert!("Alice, ".parse::<Players>().is_err());
+    }
+
+    #[test]
+    fn names_must_not_be_player_numbers() {
+        assert!("2,1".parse::<Players>().is_err());
+        assert!("Alice,2".parse::<Players>().is_err());
+        assert!("Player 2,Player 1".parse::<Players>().is_ok());
+    }
+
+    #[test]
+    fn names_must_differ_ignoring_case() {
+        assert!("Al,al".parse::<Players>().is_err());
+        assert!("Al,Al".parse::<Players>().is_err());
+        assert!("Al,Alan".parse::<Players>().is_ok());
+    }
+}
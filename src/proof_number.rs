@@ -0,0 +1,184 @@
+// Proof-number search grows the game tree towards the positions that look easiest to settle,
+// so it can often prove a result after looking at a small part of a large tree.
+//
+// Every position in the tree has a proof number, the least number of positions still to be
+// solved to prove a win for the player to move at the root, and a disproof number, the least
+// number to prove a loss. Each round expands the most-proving position: the one reached by
+// following, from the root, the move with the smallest proof number when the root player
+// is to move, and the smallest disproof number when the opponent is.
+
+use action::Move;
+use board::Position;
+use player::Player;
+use solver::{Deadline, GameResult, Outcome, Search};
+
+const INFINITY: u64 = u64::MAX;
+
+struct Node {
+    position: Position,
+    // The move leading here from the parent.
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    proof: u64,
+    disproof: u64,
+}
+
+pub struct ProofNumberSolver {
+    // The number of positions each search may add to its tree.
+    budget: u64,
+    nodes: u64,
+    deadline: Deadline,
+}
+
+impl ProofNumberSolver {
+    pub fn new(budget: u64) -> ProofNumberSolver {
+        ProofNumberSolver { budget, nodes: 0, deadline: Deadline::default() }
+    }
+}
+
+impl Search for ProofNumberSolver {
+    // Searches until the result is proven, the tree holds (about) the budgeted number of positions,
+    // or the deadline passes.
+    fn search(&mut self, root: Position) -> Outcome {
+        let player = root.to_move();
+        let mut tree = vec![new_node(root, None, None, player)];
+        let limit = self.nodes + self.budget;
+        self.nodes += 1;
+
+        while tree[0].proof != 0 && tree[0].disproof != 0 && self.nodes < limit && !self.deadline.passed(self.nodes) {
+            let mut i = 0;
+            while !tree[i].children.is_empty() {
+                let (wanted, root_to_move) = (&tree[i], tree[i].position.to_move() == player);
+                i = *wanted.children.iter().find(|&&c| if root_to_move {
+                    tree[c].proof == wanted.proof
+                } else {
+                    tree[c].disproof == wanted.disproof
+                }).expect("some move has the position's proof or disproof number");
+            }
+
+            let position = tree[i].position;
+            for m in position.legal_moves_iter() {
+                let child = new_node(position.apply(m), Some(m), Some(i), player);
+                tree.push(child);
+                let index = tree.len() - 1;
+                tree[i].children.push(index);
+                self.nodes += 1;
+            }
+
+            let mut updated = Some(i);
+            while let Some(j) = updated {
+                let proofs: Vec<_> = tree[j].children.iter().map(|&c| tree[c].proof).collect();
+                let disproofs: Vec<_> = tree[j].children.iter().map(|&c| tree[c].disproof).collect();
+                // The root player needs one winning move, and the opponent must be stopped in every move.
+                if tree[j].position.to_move() == player {
+                    tree[j].proof = min(&proofs);
+                    tree[j].disproof = sum(&disproofs);
+                } else {
+                    tree[j].proof = sum(&proofs);
+                    tree[j].disproof = min(&disproofs);
+                }
+                updated = tree[j].parent;
+            }
+        }
+
+        if tree[0].proof == 0 {
+            let winning_move = tree[0].children.iter().find(|&&c| tree[c].proof == 0).and_then(|&c| tree[c].mv);
+            Outcome::Proven(GameResult { winner: player }, winning_move)
+        } else if tree[0].disproof == 0 {
+            Outcome::Proven(GameResult { winner: player.opponent() }, None)
+        } else {
+            Outcome::Unknown(None)
+        }
+    }
+
+    fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    fn nodes(&self) -> u64 {
+        self.nodes
+    }
+}
+
+fn min(numbers: &[u64]) -> u64 {
+    numbers.iter().cloned().min().unwrap_or(INFINITY)
+}
+
+fn sum(numbers: &[u64]) -> u64 {
+    numbers.iter().fold(0, |sum, &n| sum.saturating_add(n))
+}
+
+// A position not yet expanded. If it has no moves, its player to move has lost.
+// Otherwise, the more moves its player has, the harder it is guessed to be to beat them.
+fn new_node(position: Position, mv: Option<Move>, parent: Option<usize>, player: Player) -> Node {
+    let moves = position.legal_moves_iter().count() as u64;
+    let (proof, disproof) = match (moves, position.to_move() == player) {
+        (0, true) => (INFINITY, 0),
+        (0, false) => (0, INFINITY),
+        (_, true) => (1, moves),
+        (_, false) => (moves, 1),
+    };
+    Node { position, mv, parent, children: Vec::new(), proof, disproof }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProofNumberSolver;
+    use board::{self, Board, Position};
+    use player::Player;
+    use solver::{GameResult, Outcome, Search, Solver};
+
+    fn position(s: &str, to_move: Player) -> Position {
+        let (array, board_type) = board::parse_position(s).unwrap();
+        Board::new(array, board_type).position(to_move)
+    }
+
+    #[test]
+    fn agrees_with_depth_first_search() {
+        let start = position("####./#..#./#..#./.....:Left", Player::Two);
+        let mut positions = vec![start];
+        positions.extend(start.legal_moves().into_iter().map(|m| start.apply(m)));
+        let mut solver = Solver::new();
+        for p in positions {
+            let (result, _) = solver.game_result(p);
+            match ProofNumberSolver::new(1_000_000).search(p) {
+                Outcome::Proven(proven, winning_move) => {
+                    assert_eq!(proven, result);
+                    if let Some(m) = winning_move {
+                        assert_eq!(solver.game_result(p.apply(m)).0, result);
+                    }
+                },
+                Outcome::Unknown(_) => panic!("not proven within the budget"),
+            }
+        }
+    }
+
+    #[test]
+    fn proves_the_start_with_a_winning_move() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut pns = ProofNumberSolver::new(1_000_000);
+        match pns.search(start) {
+            Outcome::Proven(result, Some(m)) => {
+                assert_eq!(result.winner, Player::One);
+                assert!(start.legal_moves().contains(&m));
+            },
+            outcome => panic!("expected a win, got {:?}", outcome),
+        }
+        assert!(pns.nodes() < 1_000_000);
+    }
+
+    #[test]
+    fn stops_at_the_budget() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut pns = ProofNumberSolver::new(100);
+        assert_eq!(pns.search(start), Outcome::Unknown(None));
+        assert!(pns.nodes() < 200);
+    }
+
+    #[test]
+    fn finished_positions_are_lost() {
+        let finished = position("#####/#.###/#.###/#####:Left", Player::Two);
+        assert_eq!(ProofNumberSolver::new(10).search(finished), Outcome::Proven(GameResult { winner: Player::One }, None));
+    }
+}
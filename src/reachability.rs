@@ -0,0 +1,114 @@
+// Whether a position could arise in a game from a given start, found by unmaking moves
+// from the position until the start is reached.
+
+use action::Move;
+use board::{BoardArray, BoardType, Board};
+use player::Player;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+// A way to reach a position: the moves from the start, and whose turn it then is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Witness {
+    pub moves: Vec<Move>,
+    pub to_move: Player,
+}
+
+type State = (BoardArray, Option<BoardType>, usize);
+
+// The shortest ways to reach `target` from `start` with `first_player` to move,
+// one for each player who can be to move in `target`. Empty if it can't be reached.
+pub fn reachable(start: &Board, first_player: Player, target: &Board) -> Vec<Witness> {
+    let start_track = start.array();
+    let contains_start = |array: &BoardArray| {
+        array.iter().zip(start_track.iter()).all(|(row, start_row)| {
+            row.iter().zip(start_row.iter()).all(|(&track, &start)| track || !start)
+        })
+    };
+
+    // Each state reached going backwards, with the state and move leading on towards the target.
+    // The last number of a state is whether an odd number of moves lead from it to the target.
+    let target_state = (target.array(), target.board_type(), 0);
+    let mut next: HashMap<State, Option<(State, Move)>> = HashMap::new();
+    next.insert(target_state, None);
+    let mut queue = VecDeque::new();
+    queue.push_back(target_state);
+    while let Some(state) = queue.pop_front() {
+        let (array, board_type, parity) = state;
+        if !contains_start(&array) || (array, board_type) == (start_track, start.board_type()) {
+            continue;
+        }
+        for (earlier, m) in Board::new(array, board_type).predecessors() {
+            let earlier_state = (earlier.array(), earlier.board_type(), 1 - parity);
+            if let Entry::Vacant(entry) = next.entry(earlier_state) {
+                entry.insert(Some((state, m)));
+                queue.push_back(earlier_state);
+            }
+        }
+    }
+
+    let mut witnesses = Vec::new();
+    for parity in 0..2 {
+        let mut state = (start_track, start.board_type(), parity);
+        if !next.contains_key(&state) {
+            continue;
+        }
+        let mut moves = Vec::new();
+        while let Some(&Some((later, m))) = next.get(&state) {
+            moves.push(m);
+            state = later;
+        }
+        let to_move = if parity == 0 { first_player } else { first_player.opponent() };
+        witnesses.push(Witness { moves, to_move });
+    }
+    witnesses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reachable;
+    use board::{self, Board};
+    use player::Player;
+
+    fn board(position: &str) -> Board {
+        let (array, board_type) = board::parse_position(position).unwrap();
+        Board::new(array, board_type)
+    }
+
+    #[test]
+    fn finds_a_witness_for_each_player() {
+        let start = board(".###./...#./...#./.....");
+        let target = board("####./#..#./#..##/....#:Left");
+        let witnesses = reachable(&start, Player::One, &target);
+        assert_eq!(witnesses.len(), 2);
+        for witness in witnesses.iter() {
+            let mut game = start.clone();
+            for &m in witness.moves.iter() {
+                assert!(game.legal_moves().contains(&m));
+                game.make_move(m);
+            }
+            assert_eq!(game.array(), target.array());
+            assert_eq!(game.board_type(), target.board_type());
+            let to_move = if witness.moves.len() % 2 == 0 { Player::One } else { Player::Two };
+            assert_eq!(witness.to_move, to_move);
+        }
+        assert_ne!(witnesses[0].to_move, witnesses[1].to_move);
+    }
+
+    #[test]
+    fn the_start_is_reached_by_no_moves() {
+        let start = board(".###./...#./...#./.....");
+        let witnesses = reachable(&start, Player::Two, &start);
+        assert_eq!(witnesses[0].moves, Vec::new());
+        assert_eq!(witnesses[0].to_move, Player::Two);
+    }
+
+    #[test]
+    fn positions_off_the_start_are_unreachable() {
+        let start = board(".###./...#./...#./.....");
+        // The start's track at (row 2, col 3) is missing.
+        assert!(reachable(&start, Player::One, &board("####./#..#./#..../.....:Left")).is_empty());
+        // The track in the lower left doesn't agree with the arrangement.
+        assert!(reachable(&start, Player::One, &board("####./#..#./#..#./#....:Middle")).is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+// Game records hold the starting position, the player who moved first,
+// and every variation that was explored, for example:
+//
+//     position .###./...#./...#./.....
+//     first 1
+//     moves Single@3,0 (OneRight@3,0 Single@3,2) Single@3,1
+//
+// Moves in parentheses are alternatives to the move just before them,
+// so here OneRight@3,0 was explored instead of the main line's Single@3,0.
+
+use action::Move;
+use board::{self, Board};
+use game::{Game, Variation};
+use player::Player;
+
+pub fn write(game: &Game) -> String {
+    let start = game.start();
+    let first = match game.first_player() {
+        Player::One => 1,
+        Player::Two => 2,
+    };
+    let mut tokens = Vec::new();
+    write_variations(&game.variation_tree(), &mut tokens);
+    let moves = tokens.join(" ").replace("( ", "(").replace(" )", ")");
+
+    let mut s = format!("position {}\n", board::format_position(&start.array(), start.board_type()));
+    s.push_str(&format!("first {}\n", first));
+    if moves.is_empty() {
+        s.push_str("moves\n");
+    } else {
+        s.push_str(&format!("moves {}\n", moves));
+    }
+    s
+}
+
+fn write_variations(variations: &[Variation], tokens: &mut Vec<String>) {
+    if let Some((main, others)) = variations.split_first() {
+        tokens.push(main.mv.notation());
+        for other in others {
+            tokens.push("(".to_string());
+            tokens.push(other.mv.notation());
+            write_variations(&other.continuations, tokens);
+            tokens.push(")".to_string());
+        }
+        write_variations(&main.continuations, tokens);
+    }
+}
+
+// Reads a game record, leaving the game at the end of its main line.
+pub fn read(s: &str) -> Result<Game, String> {
+    let mut position = None;
+    let mut first = Player::One;
+    let mut moves = String::new();
+    let mut in_moves = false;
+
+    for line in s.lines() {
+        if in_moves {
+            moves.push(' ');
+            moves.push_str(line);
+            continue;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+        match key {
+            "position" => position = Some(board::parse_position(value)?),
+            "first" => first = match value {
+                "1" => Player::One,
+                "2" => Player::Two,
+                _ => return Err(format!("Unknown player {}", value)),
+            },
+            "moves" => {
+                moves.push_str(value);
+                in_moves = true;
+            },
+            _ => return Err(format!("Unknown record field {}", key)),
+        }
+    }
+
+    let (array, board_type) = position.ok_or("Record has no position")?;
+    let board = Board::try_new(array, board_type).map_err(|e| format!("Invalid position: {}", e))?;
+    let mut game = Game::new(board, first);
+    let moves = moves.replace('(', " ( ").replace(')', " ) ");
+    let mut tokens = moves.split_whitespace();
+    read_moves(&mut game, &mut tokens, false)?;
+    Ok(game)
+}
+
+// Plays the moves of one line, returning how many were played.
+// A nested line ends at its closing parenthesis.
+fn read_moves<'a, I: Iterator<Item = &'a str>>(game: &mut Game, tokens: &mut I, nested: bool) -> Result<usize, String> {
+    let mut played = 0;
+    while let Some(token) = tokens.next() {
+        match token {
+            "(" => {
+                if played == 0 {
+                    return Err("Variation does not follow a move".to_string());
+                }
+                game.undo();
+                let variation_length = read_moves(game, tokens, true)?;
+                for _ in 0..variation_length {
+                    game.undo();
+                }
+                // The move before the variation was explored first, so it is the main line.
+                game.redo();
+            },
+            ")" => {
+                if !nested {
+                    return Err("Unmatched )".to_string());
+                }
+                return Ok(played);
+            },
+            _ => {
+                let m: Move = token.parse()?;
+                if let Err(e) = game.play(m) {
+                    return Err(format!("Illegal move {} after {} moves: {}", token, game.ply(), e));
+                }
+                played += 1;
+            },
+        }
+    }
+    if nested {
+        Err("Unmatched (".to_string())
+    } else {
+        Ok(played)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write};
+    use board::{Board,BoardArray};
+    use game::Game;
+    use player::Player;
+
+    const START_BOARD: BoardArray = [
+        [false,  true,  true,  true, false],
+        [false, false, false,  true, false],
+        [false, false, false,  true, false],
+        [false, false, false, false, false],
+    ];
+
+    #[test]
+    fn round_trips_variations() {
+        let mut game = Game::new(Board::new(START_BOARD, None), Player::Two);
+        let moves = game.legal_moves();
+        game.play(moves[0]).unwrap();
+        let reply = game.legal_moves()[1];
+        game.play(reply).unwrap();
+        game.goto(0);
+        game.play(moves[2]).unwrap();
+        let other_reply = game.legal_moves()[3];
+        game.play(other_reply).unwrap();
+        game.undo();
+        let m = game.legal_moves()[0];
+        game.play(m).unwrap();
+
+        let record = write(&game);
+        let loaded = read(&record).unwrap();
+        assert_eq!(write(&loaded), record);
+        assert_eq!(loaded.history(), vec![moves[0], reply]);
+        assert_eq!(loaded.first_player(), Player::Two);
+    }
+
+    #[test]
+    fn reads_notation() {
+        let game = read("position .###./...#./...#./.....\nfirst 1\nmoves Single@3,3 (OneRight@3,3) Single@3,4\n").unwrap();
+        assert_eq!(game.ply(), 2);
+        assert_eq!(game.player(), Player::One);
+    }
+
+    #[test]
+    fn rejects_bad_records() {
+        assert!(read("first 1\nmoves\n").is_err());
+        assert!(read("position .###./...#./...../...#.\nmoves\n").is_err());
+        assert!(read("position .###./...#./...#./.....\nmoves Single@0,0 (").is_err());
+        assert!(read("position .###./...#./...#./.....\nmoves Single@0,0)").is_err());
+        assert!(read("position .###./...#./...#./.....\nmoves (Single@0,0)").is_err());
+        assert!(read("position .###./...#./...#./.....\nmoves Single@3,0").is_err());
+        assert!(read("position .###./...#./...#./.....\nmoves OneLeft@3,0").is_err());
+        assert!(read("position .###./...#./...#./.....\nmoves TwoDown@3,4").is_err());
+    }
+}
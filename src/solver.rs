@@ -0,0 +1,330 @@
+use action::Move;
+use board::Position;
+use player::Player;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GameResult {
+    pub winner: Player,
+}
+
+impl GameResult {
+    pub fn win_for(&self, p: Player) -> bool {
+        self.winner == p
+    }
+}
+
+// What a search found out about a position.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    // The result under optimal play, and a winning move if the player to move has one.
+    Proven(GameResult, Option<Move>),
+    // The search stopped before finding the result, perhaps with a guess at the best move.
+    Unknown(Option<Move>),
+}
+
+// How long searches may run before giving up.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl Limits {
+    // When searches starting now, by a search that has searched `nodes` positions, must stop.
+    pub fn deadline(&self, nodes: u64) -> Deadline {
+        Deadline {
+            time: self.time.map(|time| Instant::now() + time),
+            nodes: self.nodes.map(|n| nodes.saturating_add(n)),
+        }
+    }
+}
+
+// The point at which searches give up. The default never comes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Deadline {
+    time: Option<Instant>,
+    // The number of positions the search will have searched in all.
+    nodes: Option<u64>,
+}
+
+impl Deadline {
+    // Whether a search that has searched `nodes` positions should stop.
+    // Reading the clock takes longer than searching a position, so it is only read every 1024 positions.
+    pub fn passed(&self, nodes: u64) -> bool {
+        self.nodes.is_some_and(|n| nodes >= n)
+            || (nodes.is_multiple_of(1024) && self.time.is_some_and(|time| Instant::now() >= time))
+    }
+
+    // Whichever of the two deadlines comes first, in time and in positions.
+    pub fn sooner(&self, other: Deadline) -> Deadline {
+        fn min<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        Deadline { time: min(self.time, other.time), nodes: min(self.nodes, other.nodes) }
+    }
+}
+
+// A way of solving positions, so that the program can use any of them.
+pub trait Search {
+    fn search(&mut self, position: Position) -> Outcome;
+
+    // Makes searches give up at `deadline`, answering with what they found so far.
+    fn set_deadline(&mut self, deadline: Deadline);
+
+    // The number of positions searched so far.
+    fn nodes(&self) -> u64;
+
+    // The number of positions whose result is remembered from one search to the next.
+    fn positions_remembered(&self) -> usize {
+        0
+    }
+}
+
+// How a move could still win, even if it loses under perfect play.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Chances {
+    pub mv: Move,
+    // The number of the opponent's replies after which the player making the move wins.
+    pub winning_replies: usize,
+    pub replies: usize,
+}
+
+impl Chances {
+    // The chance of winning against an opponent who replies at random and plays perfectly after that.
+    // A move leaving the opponent no replies has already won.
+    pub fn fraction(&self) -> f64 {
+        let (wins, replies) = self.ratio();
+        wins as f64 / replies as f64
+    }
+
+    // The fraction as a numerator and denominator, to compare without rounding.
+    fn ratio(&self) -> (usize, usize) {
+        if self.replies == 0 { (1, 1) } else { (self.winning_replies, self.replies) }
+    }
+}
+
+// The moves of the player to move, ranked by how many of the opponent's replies let them win, best first.
+// In a lost position, the first is the best swindle: the move most likely to trip up the opponent.
+// Moves with a reply the search couldn't solve can't be ranked, so they are returned apart, in move order.
+pub fn practical_chances(search: &mut dyn Search, position: Position) -> (Vec<Chances>, Vec<Move>) {
+    let player = position.to_move();
+    let mut chances = Vec::new();
+    let mut unranked = Vec::new();
+    'moves: for mv in position.legal_moves_iter() {
+        let after = position.apply(mv);
+        let mut move_chances = Chances { mv, winning_replies: 0, replies: 0 };
+        for reply in after.legal_moves_iter() {
+            match search.search(after.apply(reply)) {
+                Outcome::Proven(result, _) => if result.win_for(player) {
+                    move_chances.winning_replies += 1;
+                },
+                Outcome::Unknown(_) => {
+                    unranked.push(mv);
+                    continue 'moves;
+                },
+            }
+            move_chances.replies += 1;
+        }
+        chances.push(move_chances);
+    }
+    // a/b > c/d exactly when a*d > c*b.
+    chances.sort_by(|a, b| {
+        let ((a_wins, a_replies), (b_wins, b_replies)) = (a.ratio(), b.ratio());
+        (b_wins * a_replies).cmp(&(a_wins * b_replies))
+    });
+    (chances, unranked)
+}
+
+// Searches the game tree depth-first, counting the positions it visits.
+// The winner of each position it solves is remembered under the position's canonical form,
+// so that positions reached again, or related to one already solved by a symmetry of the board,
+// are not searched twice.
+#[derive(Default)]
+pub struct Solver {
+    nodes: u64,
+    solved: HashMap<Position, Player>,
+    // When `search` gives up; `game_result` always finishes.
+    deadline: Deadline,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::default()
+    }
+
+    // The number of positions searched so far.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    // The number of positions whose winner is remembered.
+    pub fn positions_solved(&self) -> usize {
+        self.solved.len()
+    }
+
+    // Every position whose winner is remembered, in canonical form and in order.
+    pub fn solved(&self) -> Vec<(Position, Player)> {
+        let mut solved: Vec<_> = self.solved.iter().map(|(&position, &winner)| (position, winner)).collect();
+        solved.sort();
+        solved
+    }
+
+    // Remembers the winner of a position solved elsewhere, such as in an earlier run.
+    pub fn remember(&mut self, position: Position, winner: Player) {
+        self.solved.insert(position.canonical(), winner);
+    }
+
+    // The result of the game under optimal play by both players from `position`,
+    // and a winning move if the player to move has one.
+    pub fn game_result(&mut self, position: Position) -> (GameResult, Option<Move>) {
+        self.result_before(position, Deadline::default()).expect("searches without a deadline finish")
+    }
+
+    // The result of the game from `position`, or None if the deadline passed first.
+    // Positions solved before then are still remembered.
+    fn result_before(&mut self, position: Position, deadline: Deadline) -> Option<(GameResult, Option<Move>)> {
+        self.nodes += 1;
+        if deadline.passed(self.nodes) {
+            return None;
+        }
+        let player = position.to_move();
+        // The moves are generated lazily, so a winning move found early
+        // saves generating the rest.
+        // If there are no moves at all, my opponent completed the railroad, so I lose.
+        for possible_move in position.legal_moves_iter() {
+            let winner = self.winner(position.apply(possible_move), deadline)?;
+
+            // If I have any move that forces a win, I use that move to win.
+            // We can return early from the search.
+            if winner == player {
+                return Some((GameResult { winner }, Some(possible_move)));
+            }
+        }
+
+        // I have no move that forces a win (or no move at all), therefore I must have lost.
+        Some((GameResult { winner: player.opponent() }, None))
+    }
+
+    // The winner from `position`, looked up if it was solved before.
+    fn winner(&mut self, position: Position, deadline: Deadline) -> Option<Player> {
+        let key = position.canonical();
+        if let Some(&winner) = self.solved.get(&key) {
+            return Some(winner);
+        }
+        let (result, _) = self.result_before(position, deadline)?;
+        self.solved.insert(key, result.winner);
+        Some(result.winner)
+    }
+}
+
+impl Search for Solver {
+    // Without a deadline, depth-first search always finishes, however long it takes.
+    fn search(&mut self, position: Position) -> Outcome {
+        match self.result_before(position, self.deadline) {
+            Some((result, best_move)) => Outcome::Proven(result, best_move),
+            None => Outcome::Unknown(None),
+        }
+    }
+
+    fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    fn positions_remembered(&self) -> usize {
+        self.positions_solved()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{practical_chances, Limits, Outcome, Search, Solver};
+    use board::{self, Board, Position};
+    use player::Player;
+    use std::time::Duration;
+
+    fn position(s: &str, to_move: Player) -> Position {
+        let (array, board_type) = board::parse_position(s).unwrap();
+        Board::new(array, board_type).position(to_move)
+    }
+
+    #[test]
+    fn node_limit_stops_the_search() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut solver = Solver::new();
+        solver.set_deadline(Limits { time: None, nodes: Some(100) }.deadline(solver.nodes()));
+        assert_eq!(solver.search(start), Outcome::Unknown(None));
+        assert_eq!(solver.nodes(), 100);
+
+        // What was solved before the limit is right, and a later search without one can use it.
+        let mut fresh = Solver::new();
+        for (p, winner) in solver.solved() {
+            assert_eq!(fresh.game_result(p).0.winner, winner);
+        }
+        solver.set_deadline(Limits::default().deadline(solver.nodes()));
+        match solver.search(start) {
+            Outcome::Proven(result, _) => assert_eq!(result, fresh.game_result(start).0),
+            outcome => panic!("expected a proof, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn time_limit_stops_the_search() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut solver = Solver::new();
+        solver.set_deadline(Limits { time: Some(Duration::from_secs(0)), nodes: None }.deadline(solver.nodes()));
+        assert_eq!(solver.search(start), Outcome::Unknown(None));
+        assert!(solver.nodes() <= 1024);
+    }
+
+    #[test]
+    fn sooner_takes_each_limit_from_either_deadline() {
+        let by_time = Limits { time: Some(Duration::from_secs(0)), nodes: None }.deadline(0);
+        let by_nodes = Limits { time: None, nodes: Some(10) }.deadline(0);
+        let both = by_time.sooner(by_nodes);
+        assert!(both.passed(10));
+        assert!(both.passed(1024));
+        assert!(!both.passed(5));
+        assert_eq!(by_nodes.sooner(Limits::default().deadline(0)), by_nodes);
+    }
+
+    #[test]
+    fn practical_chances_count_replies_that_go_wrong() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut solver = Solver::new();
+        let (_, winning_move) = solver.game_result(start);
+        // Player 2 has lost, so every move loses, but some give Player 1 more chances to go wrong.
+        let lost = start.apply(winning_move.unwrap());
+        let (chances, unranked) = practical_chances(&mut solver, lost);
+        assert_eq!(chances.len(), lost.legal_moves().len());
+        assert!(unranked.is_empty());
+        for c in chances.iter() {
+            let after = lost.apply(c.mv);
+            let replies = after.legal_moves();
+            let winning = replies.iter().filter(|&&r| solver.game_result(after.apply(r)).0.win_for(Player::Two)).count();
+            assert_eq!((c.winning_replies, c.replies), (winning, replies.len()));
+            assert!(c.winning_replies < c.replies);
+        }
+        assert!(chances.windows(2).all(|w| w[0].fraction() >= w[1].fraction()));
+        assert!(chances[0].winning_replies > 0);
+    }
+
+    #[test]
+    fn practical_chances_report_moves_they_cannot_rank() {
+        let start = position(".###./...#./...#./.....", Player::One);
+        let mut solver = Solver::new();
+        solver.set_deadline(Limits { time: None, nodes: Some(1) }.deadline(0));
+        let (chances, unranked) = practical_chances(&mut solver, start);
+        assert!(!unranked.is_empty());
+        assert_eq!(chances.len() + unranked.len(), start.legal_moves().len());
+        assert!(unranked.iter().all(|m| chances.iter().all(|c| c.mv != *m)));
+    }
+}
@@ -0,0 +1,220 @@
+// A tablebase holds the result of every position reachable from a start:
+// who wins with perfect play, and how many more moves the game then lasts.
+//
+// On disk it is the bytes "MONOTB", the rules version and the number of entries
+// (each a little-endian u32), then the entries in increasing order. Each entry is a u32
+// holding a position as packed by `Position::encode` in its upper 24 bits and its value
+// in the lower 8: the top bit set if the player to move wins, and the rest the distance.
+
+use action::Move;
+use board::Position;
+use cache::RULES_VERSION;
+use player::Player;
+use std::collections::HashMap;
+
+const MAGIC: &[u8] = b"MONOTB";
+const WINS: u32 = 0x80;
+
+// The result of a position with perfect play, where the winner wins as quickly as possible
+// and the loser holds out as long as possible.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub winner: Player,
+    // The number of moves left in the game.
+    pub distance: u32,
+}
+
+pub struct Tablebase {
+    entries: Vec<u32>,
+}
+
+impl Tablebase {
+    // Solves every position reachable from `start`.
+    // Moves only ever add track, so positions with more track are solved first,
+    // from the finished positions back towards the start.
+    pub fn generate(start: Position) -> Tablebase {
+        let mut positions = vec![start];
+        let mut values = HashMap::new();
+        values.insert(start, 0);
+        let mut i = 0;
+        while i < positions.len() {
+            let position = positions[i];
+            for m in position.legal_moves_iter() {
+                let next = position.apply(m);
+                if values.insert(next, 0).is_none() {
+                    positions.push(next);
+                }
+            }
+            i += 1;
+        }
+
+        positions.sort_by_key(track_length);
+        for position in positions.iter().rev() {
+            let value = value_from_replies(position.legal_moves_iter().map(|m| values[&position.apply(m)]));
+            values.insert(*position, value);
+        }
+
+        let mut entries: Vec<_> = values.iter().map(|(position, value)| position.encode() << 8 | value).collect();
+        entries.sort();
+        Tablebase { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The result of a position, if it is in the tablebase.
+    pub fn query(&self, position: Position) -> Option<Entry> {
+        let key = position.encode();
+        let i = self.entries.binary_search_by_key(&key, |entry| entry >> 8).ok()?;
+        let value = self.entries[i] & 0xff;
+        Some(Entry {
+            winner: if value & WINS != 0 { position.to_move() } else { position.to_move().opponent() },
+            distance: value & !WINS,
+        })
+    }
+
+    // A move keeping the result of the position: the quickest win if there is one,
+    // otherwise the longest loss.
+    pub fn best_move(&self, position: Position) -> Option<Move> {
+        let entry = self.query(position)?;
+        position.legal_moves_iter().find(|&m| self.query(position.apply(m)) == Some(Entry {
+            winner: entry.winner,
+            distance: entry.distance - 1,
+        }))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&RULES_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tablebase, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("Not a tablebase".to_string());
+        }
+        let words: Vec<_> = bytes[MAGIC.len()..].chunks(4).map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        }).collect();
+        if bytes.len() % 4 != MAGIC.len() % 4 || words.len() < 2 {
+            return Err("Truncated tablebase".to_string());
+        }
+        if words[0] != RULES_VERSION {
+            return Err(format!("Generated for rules version {}, but these are version {}", words[0], RULES_VERSION));
+        }
+        let entries = words[2..].to_vec();
+        if entries.len() != words[1] as usize {
+            return Err(format!("Expected {} entries, found {}", words[1], entries.len()));
+        }
+        if entries.windows(2).any(|pair| pair[0] >> 8 >= pair[1] >> 8) {
+            return Err("Entries out of order".to_string());
+        }
+        Ok(Tablebase { entries })
+    }
+}
+
+fn track_length(position: &Position) -> usize {
+    position.array().iter().map(|row| row.iter().filter(|&&x| x).count()).sum()
+}
+
+// The value of a position given the values of the positions after each of its moves.
+fn value_from_replies<I: Iterator<Item = u32>>(replies: I) -> u32 {
+    let mut quickest_win = None;
+    let mut longest_loss = 0;
+    for reply in replies {
+        if reply & WINS == 0 {
+            // The opponent loses after this move.
+            let distance = (reply & !WINS) + 1;
+            if quickest_win.is_none_or(|d| distance < d) {
+                quickest_win = Some(distance);
+            }
+        } else {
+            longest_loss = longest_loss.max((reply & !WINS) + 1);
+        }
+    }
+    match quickest_win {
+        Some(distance) => WINS | distance,
+        // With no moves at all, the game is already over and lost.
+        None => longest_loss,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, Tablebase};
+    use board::{self, Board, Position};
+    use player::Player;
+    use solver::Solver;
+
+    fn start() -> Position {
+        let (array, board_type) = board::parse_position(".###./...#./...#./.....").unwrap();
+        Board::new(array, board_type).position(Player::One)
+    }
+
+    #[test]
+    fn agrees_with_the_solver() {
+        let tablebase = Tablebase::generate(start());
+        let mut positions = vec![start()];
+        for m in start().legal_moves() {
+            let next = start().apply(m);
+            positions.push(next);
+            positions.extend(next.legal_moves().into_iter().map(|reply| next.apply(reply)));
+        }
+        let mut solver = Solver::new();
+        for position in positions {
+            let entry = tablebase.query(position).unwrap();
+            assert_eq!(entry.winner, solver.game_result(position).0.winner);
+        }
+    }
+
+    #[test]
+    fn best_moves_count_down_to_the_end() {
+        let tablebase = Tablebase::generate(start());
+        let mut position = start();
+        let entry = tablebase.query(position).unwrap();
+        assert_eq!(entry.winner, Player::One);
+        for distance in (0..entry.distance).rev() {
+            position = position.apply(tablebase.best_move(position).unwrap());
+            assert_eq!(tablebase.query(position).unwrap().distance, distance);
+        }
+        assert!(position.legal_moves().is_empty());
+        assert_eq!(tablebase.query(position), Some(Entry { winner: position.to_move().opponent(), distance: 0 }));
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let (array, board_type) = board::parse_position("####./#..#./#..#./.....:Left").unwrap();
+        let position = Board::new(array, board_type).position(Player::Two);
+        let tablebase = Tablebase::generate(position);
+        let bytes = tablebase.to_bytes();
+        assert_eq!(bytes.len(), 14 + 4 * tablebase.len());
+        let read = Tablebase::from_bytes(&bytes).unwrap();
+        assert_eq!(read.query(position), tablebase.query(position));
+
+        assert!(Tablebase::from_bytes(b"MONOTB").is_err());
+        assert!(Tablebase::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Tablebase::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+        let mut other_rules = bytes.clone();
+        other_rules[6] = 0;
+        assert!(Tablebase::from_bytes(&other_rules).is_err());
+    }
+
+    #[test]
+    fn positions_not_reachable_are_missing() {
+        let (array, board_type) = board::parse_position("####./#..#./#..#./.....:Left").unwrap();
+        let tablebase = Tablebase::generate(Board::new(array, board_type).position(Player::Two));
+        assert_eq!(tablebase.query(start()), None);
+        assert_eq!(tablebase.query(Board::new(array, board_type).position(Player::One)), None);
+    }
+}